@@ -0,0 +1,98 @@
+//! Compares streaming catalog parsing with querying the fields of each component from the
+//! whole document through XPath, and with validating each component as a separate metainfo
+//! document.
+
+extern crate appstream;
+#[macro_use]
+extern crate criterion;
+extern crate xpath_reader;
+
+use appstream::catalog::Components;
+use appstream::metainfo::Metainfo;
+use criterion::{Criterion, Throughput};
+use xpath_reader::Reader;
+
+const COMPONENT: &str = include_str!("fixtures/component.xml");
+const NUM_COMPONENTS: usize = 500;
+const FIELDS: &[&str] = &[
+    "id",
+    "name",
+    "summary",
+    "pkgname",
+    "metadata_license",
+    "license",
+];
+
+fn components(num: usize) -> Vec<String> {
+    (0..num)
+        .map(|n| COMPONENT.replace("{n}", &n.to_string()))
+        .collect()
+}
+
+fn catalog(components: &[String]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<components version=\"0.14\" origin=\"bench\">\n");
+    for component in components {
+        xml.push_str(component);
+    }
+    xml.push_str("</components>\n");
+    xml
+}
+
+fn parse_catalog(c: &mut Criterion) {
+    let components = components(NUM_COMPONENTS);
+    let xml = catalog(&components);
+
+    let mut group = c.benchmark_group("catalog");
+    group.throughput(Throughput::Bytes(xml.len() as u64));
+    group.sample_size(20);
+
+    group.bench_function("stream", |b| {
+        b.iter(|| {
+            let components = Components::new(xml.as_bytes()).unwrap();
+            assert_eq!(components.filter(|c| c.is_ok()).count(), NUM_COMPONENTS);
+        })
+    });
+
+    #[cfg(feature = "parallel")]
+    group.bench_function("parallel", |b| {
+        b.iter(|| {
+            let components = Components::new(xml.as_bytes()).unwrap().parallel();
+            assert_eq!(components.filter(|c| c.is_ok()).count(), NUM_COMPONENTS);
+        })
+    });
+
+    // The whole catalog in one document, with each field read relative to its component.
+    group.bench_function("xpath", |b| {
+        b.iter(|| {
+            let reader = Reader::from_str(&xml, None).unwrap();
+            let components = reader.with_nodeset_eval("/components/component").unwrap();
+            let nodes = components.anchor_nodeset().document_order();
+            for &node in &nodes {
+                let component = Reader::from_node(node, None);
+                for field in FIELDS {
+                    let xpath = format!("{}/text()", field);
+                    let values: Vec<String> = component.read(xpath.as_str()).unwrap();
+                    assert!(!values.is_empty());
+                }
+            }
+            assert_eq!(nodes.len(), NUM_COMPONENTS);
+        })
+    });
+
+    // The lower bound for streaming: the same components, already split into documents.
+    group.bench_function("metainfo", |b| {
+        b.iter(|| {
+            let parsed = components
+                .iter()
+                .filter(|xml| Metainfo::from_str(xml.as_str()).validate().is_ok())
+                .count();
+            assert_eq!(parsed, NUM_COMPONENTS);
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse_catalog);
+criterion_main!(benches);
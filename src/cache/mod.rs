@@ -0,0 +1,582 @@
+//! A compiled binary cache of parsed components, so that they need not be re-parsed from XML
+//! on every start.
+//!
+//! The cache is memory-mapped when opened. Components are only decoded when they are read,
+//! with their strings interned into a table shared by the whole file.
+
+mod de;
+mod ser;
+
+use std::convert::TryFrom;
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str;
+use std::time::UNIX_EPOCH;
+
+use memmap2::Mmap;
+use serde::de::Error as DeError;
+use serde::ser::Error as SerError;
+use serde::Deserialize;
+
+use self::de::Decoder;
+use self::ser::{Encoder, Interner};
+use AppStream;
+
+const MAGIC: &[u8] = b"ASCACHE\0";
+
+/// Version of the file layout and of the encoding of the model types.
+///
+/// This must be bumped whenever a serialized type changes shape.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A memory-mapped component cache.
+#[derive(Debug)]
+pub struct Cache {
+    map: Mmap,
+    sources: Vec<Source>,
+    strings: Table,
+    components: Table,
+    data: usize,
+}
+
+impl Cache {
+    /// Compiles `components` into a cache file at `path`.
+    ///
+    /// The `sources` the components were parsed from are recorded, so that the cache can tell
+    /// when it is out of date. Sources may be files, which are compared by modification time, or
+    /// directories, whose listing is recorded as well so that newly added files are noticed.
+    pub fn write<P, S>(path: P, sources: &[S], components: &[AppStream]) -> Result<(), CacheError>
+    where
+        P: AsRef<Path>,
+        S: AsRef<Path>,
+    {
+        let mut interner = Interner::default();
+        let entries = components
+            .iter()
+            .map(|component| Encoder::encode(component, &mut interner))
+            .collect::<Result<Vec<_>, _>>()?;
+        let strings = interner.strings();
+
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        put_u32(&mut out, FORMAT_VERSION);
+        put_u32(&mut out, to_u32(sources.len())?);
+        put_u32(&mut out, to_u32(strings.len())?);
+        put_u32(&mut out, to_u32(entries.len())?);
+
+        for source in sources {
+            Source::stat(source.as_ref())?.write(&mut out)?;
+        }
+
+        let mut offset = 0;
+        for len in strings
+            .iter()
+            .map(|s| s.len())
+            .chain(entries.iter().map(|e| e.len()))
+        {
+            put_u32(&mut out, to_u32(offset)?);
+            put_u32(&mut out, to_u32(len)?);
+            offset += len;
+        }
+
+        for s in strings {
+            out.extend_from_slice(s.as_bytes());
+        }
+        for entry in &entries {
+            out.extend_from_slice(entry);
+        }
+
+        // Replace the old cache atomically, as other processes may have it mapped.
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, &out)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Maps the cache file at `path`.
+    ///
+    /// Fails with `CacheError::Stale` if any source has changed since the cache was written.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, CacheError> {
+        let file = File::open(path)?;
+        // The cache is replaced by renaming rather than written in place, so the mapped file
+        // does not change underneath us.
+        let map = unsafe { Mmap::map(&file)? };
+
+        let mut input = &map[..];
+        if take(&mut input, MAGIC.len())? != MAGIC {
+            return Err(CacheError::InvalidFormat);
+        }
+
+        let version = take_u32(&mut input)?;
+        if version != FORMAT_VERSION {
+            return Err(CacheError::UnsupportedVersion(version));
+        }
+
+        let num_sources = take_u32(&mut input)? as usize;
+        let num_strings = take_u32(&mut input)? as usize;
+        let num_components = take_u32(&mut input)? as usize;
+
+        let mut sources = Vec::with_capacity(num_sources.min(input.len() / Source::MIN_LEN));
+        for _ in 0..num_sources {
+            sources.push(Source::read(&mut input)?);
+        }
+
+        let strings = Table {
+            offset: map.len() - input.len(),
+            len: num_strings,
+        };
+        let components = Table {
+            offset: strings.end().ok_or(CacheError::InvalidFormat)?,
+            len: num_components,
+        };
+        let data = components.end().ok_or(CacheError::InvalidFormat)?;
+        if data > map.len() {
+            return Err(CacheError::InvalidFormat);
+        }
+
+        let cache = Cache {
+            map,
+            sources,
+            strings,
+            components,
+            data,
+        };
+
+        if let Some(source) = cache.stale_source() {
+            return Err(CacheError::Stale(source.display().to_string()));
+        }
+
+        Ok(cache)
+    }
+
+    /// Returns whether every source still has the modification time recorded in the cache.
+    pub fn is_fresh(&self) -> bool {
+        self.stale_source().is_none()
+    }
+
+    /// Returns the files the cached components were parsed from.
+    pub fn sources(&self) -> Vec<&Path> {
+        self.sources.iter().map(|s| s.path.as_path()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.len == 0
+    }
+
+    /// Decodes the component at `index`.
+    pub fn get(&self, index: usize) -> Option<Result<AppStream, CacheError>> {
+        if index >= self.components.len {
+            return None;
+        }
+
+        Some(self.decode(index))
+    }
+
+    /// Decodes every component, in the order they were written.
+    pub fn iter<'a>(&'a self) -> Iter<'a> {
+        Iter {
+            cache: self,
+            next: 0,
+        }
+    }
+
+    fn stale_source(&self) -> Option<&Path> {
+        self.sources
+            .iter()
+            .find(|&source| Source::stat(&source.path).ok().as_ref() != Some(source))
+            .map(|source| source.path.as_path())
+    }
+
+    fn string_table<'a>(&'a self) -> Strings<'a> {
+        Strings {
+            table: &self.map[self.strings.offset..self.components.offset],
+            data: &self.map[self.data..],
+        }
+    }
+
+    fn decode(&self, index: usize) -> Result<AppStream, CacheError> {
+        let table = &self.map[self.components.offset..self.data];
+        let entry = entry(table, &self.map[self.data..], index)?;
+
+        let strings = self.string_table();
+        let mut decoder = Decoder::new(entry, &strings);
+        Ok(AppStream::deserialize(&mut decoder)?)
+    }
+}
+
+/// An iterator over the components of a `Cache`.
+#[derive(Debug)]
+pub struct Iter<'a> {
+    cache: &'a Cache,
+    next: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Result<AppStream, CacheError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.cache.get(self.next)?;
+        self.next += 1;
+        Some(item)
+    }
+}
+
+/// A source file or directory and its modification time, as seconds and nanoseconds since the
+/// epoch.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Source {
+    path: PathBuf,
+    mtime: (u64, u32),
+    /// The sorted file names in a directory source.
+    entries: Option<Vec<String>>,
+}
+
+impl Source {
+    /// The encoded size of a source with an empty path and no listing.
+    const MIN_LEN: usize = 20;
+
+    /// Written instead of the number of entries for sources which are not directories.
+    const NO_ENTRIES: u32 = u32::MAX;
+
+    fn stat(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let since_epoch = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let entries = if metadata.is_dir() {
+            let mut entries = fs::read_dir(path)?
+                .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            Some(entries)
+        } else {
+            None
+        };
+
+        Ok(Source {
+            path: path.to_path_buf(),
+            mtime: (since_epoch.as_secs(), since_epoch.subsec_nanos()),
+            entries,
+        })
+    }
+
+    fn write(&self, out: &mut Vec<u8>) -> Result<(), CacheError> {
+        let path = self.path.to_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "source path is not valid UTF-8",
+            )
+        })?;
+        put_str(out, path)?;
+        put_u64(out, self.mtime.0);
+        put_u32(out, self.mtime.1);
+
+        match self.entries {
+            Some(ref entries) => {
+                let len = to_u32(entries.len())?;
+                if len == Source::NO_ENTRIES {
+                    return Err(CacheError::TooLarge);
+                }
+                put_u32(out, len);
+                for entry in entries {
+                    put_str(out, entry)?;
+                }
+            }
+            None => put_u32(out, Source::NO_ENTRIES),
+        }
+        Ok(())
+    }
+
+    fn read(input: &mut &[u8]) -> Result<Self, CacheError> {
+        let path = take_str(input)?;
+        let secs = take_u64(input)?;
+        let nanos = take_u32(input)?;
+
+        let entries = match take_u32(input)? {
+            Source::NO_ENTRIES => None,
+            num => {
+                // Every entry takes at least its four byte length.
+                let mut entries = Vec::with_capacity((num as usize).min(input.len() / 4));
+                for _ in 0..num {
+                    entries.push(take_str(input)?.to_string());
+                }
+                Some(entries)
+            }
+        };
+
+        Ok(Source {
+            path: PathBuf::from(path),
+            mtime: (secs, nanos),
+            entries,
+        })
+    }
+}
+
+/// A table of `(offset, length)` pairs, each pointing into the data section.
+#[derive(Clone, Copy, Debug)]
+struct Table {
+    offset: usize,
+    len: usize,
+}
+
+impl Table {
+    const ENTRY_LEN: usize = 8;
+
+    /// Returns the offset after the table, or `None` if a corrupt length overflows.
+    fn end(&self) -> Option<usize> {
+        self.len
+            .checked_mul(Table::ENTRY_LEN)
+            .and_then(|len| self.offset.checked_add(len))
+    }
+}
+
+/// The interned strings of a mapped cache.
+pub(crate) struct Strings<'a> {
+    table: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> Strings<'a> {
+    fn get(&self, index: u32) -> Result<&'a str, CodecError> {
+        let bytes = entry(self.table, self.data, index as usize)
+            .map_err(|_| CodecError::new(format!("invalid string index {}", index)))?;
+        str::from_utf8(bytes).map_err(|e| CodecError::new(e.to_string()))
+    }
+}
+
+fn entry<'a>(table: &[u8], data: &'a [u8], index: usize) -> Result<&'a [u8], CacheError> {
+    let mut input = table
+        .get(index * Table::ENTRY_LEN..)
+        .ok_or(CacheError::InvalidFormat)?;
+    let offset = take_u32(&mut input)? as usize;
+    let len = take_u32(&mut input)? as usize;
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(CacheError::InvalidFormat)
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], CacheError> {
+    if input.len() < len {
+        return Err(CacheError::InvalidFormat);
+    }
+
+    let (head, rest) = input.split_at(len);
+    *input = rest;
+    Ok(head)
+}
+
+fn take_str<'a>(input: &mut &'a [u8]) -> Result<&'a str, CacheError> {
+    let len = take_u32(input)? as usize;
+    str::from_utf8(take(input, len)?).map_err(|_| CacheError::InvalidFormat)
+}
+
+fn take_u32(input: &mut &[u8]) -> Result<u32, CacheError> {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(take(input, 4)?);
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn take_u64(input: &mut &[u8]) -> Result<u64, CacheError> {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(take(input, 8)?);
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn to_u32(n: usize) -> Result<u32, CacheError> {
+    u32::try_from(n).map_err(|_| CacheError::TooLarge)
+}
+
+fn put_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_str(out: &mut Vec<u8>, s: &str) -> Result<(), CacheError> {
+    put_u32(out, to_u32(s.len())?);
+    out.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+fn put_u64(out: &mut Vec<u8>, v: u64) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+#[derive(Debug, Fail)]
+pub enum CacheError {
+    #[fail(display = "Failed to access cache: {}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "Not a component cache, or truncated")]
+    InvalidFormat,
+    #[fail(display = "Unsupported cache format version {}", _0)]
+    UnsupportedVersion(u32),
+    #[fail(display = "Cache is older than its source `{}`", _0)]
+    Stale(String),
+    #[fail(display = "Cache would exceed the 4 GiB limit of its format")]
+    TooLarge,
+    #[fail(display = "Invalid cache entry: {}", _0)]
+    Codec(#[cause] CodecError),
+}
+
+impl From<io::Error> for CacheError {
+    fn from(e: io::Error) -> Self {
+        CacheError::Io(e)
+    }
+}
+
+impl From<CodecError> for CacheError {
+    fn from(e: CodecError) -> Self {
+        CacheError::Codec(e)
+    }
+}
+
+/// An error encoding or decoding a single cache entry.
+#[derive(Clone, Debug)]
+pub struct CodecError(String);
+
+impl CodecError {
+    fn new<S: Into<String>>(message: S) -> Self {
+        CodecError(message.into())
+    }
+}
+
+impl Display for CodecError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        let CodecError(ref message) = *self;
+        message.fmt(fmt)
+    }
+}
+
+impl StdError for CodecError {}
+
+impl SerError for CodecError {
+    fn custom<T: Display>(msg: T) -> Self {
+        CodecError::new(msg.to_string())
+    }
+}
+
+impl DeError for CodecError {
+    fn custom<T: Display>(msg: T) -> Self {
+        CodecError::new(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metainfo::Metainfo;
+    use test_util::TempDir;
+
+    const METAINFO: &str = r#"
+        <component type="desktop-application">
+            <id>org.example.App</id>
+            <name>App</name>
+            <name xml:lang="de">Anwendung</name>
+            <summary>Does things</summary>
+            <pkgname>app</pkgname>
+            <metadata_license>CC0-1.0</metadata_license>
+            <license>GPL-3.0-or-later OR MIT</license>
+            <categories>
+                <category>Utility</category>
+            </categories>
+            <bundle type="flatpak">app/org.example.App/x86_64/stable</bundle>
+        </component>
+    "#;
+
+    #[test]
+    fn roundtrip() {
+        let dir = TempDir::new("cache");
+        let source = dir.write("org.example.App.metainfo.xml", METAINFO);
+
+        let component = Metainfo::from_str(METAINFO).validate().unwrap();
+        let components = vec![component.clone(), component.clone()];
+        let path = dir.path().join("components.cache");
+        Cache::write(&path, &[&source], &components).unwrap();
+
+        let cache = Cache::open(&path).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.sources(), vec![source.as_path()]);
+        assert_eq!(cache.get(1).unwrap().unwrap(), component);
+        assert!(cache.get(2).is_none());
+
+        // Both components share every string.
+        Cache::write(&path, &[&source], &components[..1]).unwrap();
+        let single = Cache::open(&path).unwrap();
+        assert_eq!(single.strings.len, cache.strings.len);
+    }
+
+    #[test]
+    fn invalidated_by_source() {
+        let dir = TempDir::new("cache");
+        let source = dir.write("catalog.xml", "<components/>");
+
+        let path = dir.path().join("components.cache");
+        Cache::write(&path, &[&source], &[]).unwrap();
+        assert!(Cache::open(&path).unwrap().is_fresh());
+
+        fs::remove_file(&source).unwrap();
+        match Cache::open(&path) {
+            Err(CacheError::Stale(ref s)) => assert_eq!(s, &source.display().to_string()),
+            other => panic!("expected stale cache, got {:?}", other),
+        }
+
+        fs::write(&path, b"not a cache").unwrap();
+        match Cache::open(&path) {
+            Err(CacheError::InvalidFormat) => {}
+            other => panic!("expected invalid format, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalidated_by_new_file() {
+        let dir = TempDir::new("cache");
+        let catalogs = dir.path().join("catalogs");
+        dir.write("catalogs/fedora.xml", "<components/>");
+
+        let path = dir.path().join("components.cache");
+        Cache::write(&path, &[&catalogs], &[]).unwrap();
+        assert!(Cache::open(&path).unwrap().is_fresh());
+
+        dir.write("catalogs/flathub.xml", "<components/>");
+        match Cache::open(&path) {
+            Err(CacheError::Stale(ref s)) => assert_eq!(s, &catalogs.display().to_string()),
+            other => panic!("expected stale cache, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn corrupt_counts() {
+        let dir = TempDir::new("cache");
+        let path = dir.path().join("components.cache");
+
+        let mut data = MAGIC.to_vec();
+        put_u32(&mut data, FORMAT_VERSION);
+        for _ in 0..3 {
+            put_u32(&mut data, u32::MAX);
+        }
+        fs::write(&path, &data).unwrap();
+        match Cache::open(&path) {
+            Err(CacheError::InvalidFormat) => {}
+            other => panic!("expected invalid format, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn too_large() {
+        assert_eq!(to_u32(u32::MAX as usize).unwrap(), u32::MAX);
+        match to_u32(u32::MAX as usize + 1) {
+            Err(CacheError::TooLarge) => {}
+            other => panic!("expected too large, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Transparent decompression of catalog files.
+
+use std::io::{self, BufRead, BufReader};
+
+use flate2::bufread::MultiGzDecoder;
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// The compression of a catalog file, e.g. `*.xml.gz` or `*.xml.zst`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Detects the compression from the magic bytes at the start of a file.
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if header.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Wraps `reader` in a decoder matching its compression, without consuming any input.
+///
+/// Uncompressed input is passed through as is.
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    let compression = Compression::detect(reader.fill_buf()?);
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Compression::Zstd => Box::new(BufReader::new(ZstdDecoder::with_buffer(reader)?)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use flate2::write::GzEncoder;
+    use zstd;
+
+    use super::*;
+
+    const XML: &str = "<components version=\"0.14\"/>";
+
+    fn roundtrip(data: &[u8], expected: Compression) {
+        assert_eq!(Compression::detect(data), expected);
+
+        let mut text = String::new();
+        decompress(data).unwrap().read_to_string(&mut text).unwrap();
+        assert_eq!(text, XML);
+    }
+
+    #[test]
+    fn detect_and_decompress() {
+        roundtrip(XML.as_bytes(), Compression::None);
+
+        let mut gz = GzEncoder::new(Vec::new(), ::flate2::Compression::default());
+        gz.write_all(XML.as_bytes()).unwrap();
+        roundtrip(&gz.finish().unwrap(), Compression::Gzip);
+
+        let zst = zstd::encode_all(XML.as_bytes(), 0).unwrap();
+        roundtrip(&zst, Compression::Zstd);
+    }
+}
@@ -0,0 +1,456 @@
+//! Catalogs which list the components available from a distribution, e.g. the files in
+//! `/usr/share/swcatalog/xml` and `/var/lib/app-info/xmls`.
+//!
+//! Catalogs are read as a stream of XML events, so only one component is held in memory at a
+//! time. Each component is then parsed on its own, like a metainfo file.
+
+pub mod compression;
+#[cfg(feature = "parallel")]
+mod parallel;
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader as XmlReader, Writer as XmlWriter};
+
+use self::compression::decompress;
+#[cfg(feature = "parallel")]
+pub use self::parallel::ParComponents;
+use field::id::TldPolicy;
+use field::ParseOptions;
+use location::Location;
+use metainfo::{Metainfo, ParseError};
+use AppStream;
+
+/// A parsed `<components>` catalog.
+#[derive(Clone, Debug)]
+pub struct Catalog {
+    origin: Option<String>,
+    priority: i32,
+    components: Vec<AppStream>,
+}
+
+impl Catalog {
+    /// Reads the catalog at `path`, decompressing it if needed.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, CatalogError> {
+        Catalog::from_components(Components::from_path(path)?)
+    }
+
+    /// Reads a catalog from `reader`, decompressing gzip and zstd input as it is read.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, CatalogError> {
+        Catalog::from_components(Components::new(reader)?)
+    }
+
+    /// Parses an uncompressed catalog document.
+    pub fn parse(xml: &str) -> Result<Self, CatalogError> {
+        Catalog::from_reader(xml.as_bytes())
+    }
+
+    /// Collects the components of `stream`, e.g. one created with a different `TldPolicy`.
+    pub fn from_components(stream: Components) -> Result<Self, CatalogError> {
+        #[cfg(feature = "parallel")]
+        let mut stream = stream.parallel();
+        #[cfg(not(feature = "parallel"))]
+        let mut stream = stream;
+
+        let components = stream.by_ref().collect::<Result<_, _>>()?;
+        Ok(Catalog {
+            origin: stream.origin().map(String::from),
+            priority: stream.priority(),
+            components,
+        })
+    }
+
+    /// Returns the `origin` attribute of the catalog, e.g. `fedora`.
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    /// Returns the `priority` attribute of the catalog, which defaults to 0.
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    pub fn components(&self) -> &[AppStream] {
+        self.components.as_slice()
+    }
+}
+
+/// An iterator which parses the components of a catalog one at a time, in document order.
+///
+/// An invalid component is reported as an error and skipped; malformed XML ends the iteration.
+pub struct Components<'a> {
+    reader: XmlReader<Tracked<Box<dyn BufRead + 'a>>>,
+    buf: Vec<u8>,
+    origin: Option<String>,
+    priority: i32,
+    index: usize,
+    done: bool,
+    options: ParseOptions,
+}
+
+impl<'a> Components<'a> {
+    /// Streams the components from `reader`, decompressing gzip and zstd input as it is read.
+    pub fn new<R: BufRead + 'a>(reader: R) -> io::Result<Self> {
+        let reader = Tracked {
+            inner: decompress(reader)?,
+            position: Position::default(),
+        };
+
+        Ok(Components {
+            reader: XmlReader::from_reader(reader),
+            buf: Vec::new(),
+            origin: None,
+            priority: 0,
+            index: 0,
+            done: false,
+            options: ParseOptions::default(),
+        })
+    }
+
+    /// Selects which top-level domains are accepted in component IDs, as
+    /// `Metainfo::with_tld_policy` does.
+    pub fn with_tld_policy(mut self, policy: TldPolicy) -> Self {
+        self.options.set_tld_policy(policy);
+        self
+    }
+
+    /// Replaces all parser options, e.g. with those of the pool the catalog is loaded into.
+    pub(crate) fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Streams the components from the catalog at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Components<'static>> {
+        let file = File::open(path)?;
+        Components::new(BufReader::new(file))
+    }
+
+    /// Returns the `origin` attribute of the catalog, once the root element has been read.
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    /// Returns the `priority` attribute of the catalog, once the root element has been read.
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn location(&self) -> Location {
+        self.reader.get_ref().position.next
+    }
+
+    /// Returns the location of the tag which was read last.
+    fn tag_location(&self) -> Location {
+        self.reader.get_ref().position.last_tag
+    }
+
+    fn xml_error<E: ToString>(&mut self, location: Location, error: E) -> CatalogError {
+        self.done = true;
+        CatalogError::Xml {
+            location,
+            message: error.to_string(),
+        }
+    }
+
+    /// Copies the events of the component which has just started into a standalone document.
+    fn read_component(&mut self, start: BytesStart) -> Result<String, CatalogError> {
+        let mut writer = XmlWriter::new(Vec::new());
+        writer
+            .write_event(Event::Start(start))
+            .map_err(|e| self.xml_error(self.location(), e))?;
+
+        let mut buf = Vec::new();
+        let mut depth = 1;
+        while depth > 0 {
+            let location = self.location();
+            buf.clear();
+            let event = match self.reader.read_event_into(&mut buf) {
+                Ok(Event::Eof) => return Err(self.xml_error(location, "unexpected end of file")),
+                Ok(event) => event,
+                Err(e) => return Err(self.xml_error(location, e)),
+            };
+
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                _ => {}
+            }
+
+            writer
+                .write_event(event)
+                .map_err(|e| self.xml_error(location, e))?;
+        }
+
+        String::from_utf8(writer.into_inner()).map_err(|e| self.xml_error(self.location(), e))
+    }
+
+    /// Reads the next component without parsing it.
+    fn next_raw(&mut self) -> Option<Result<RawComponent, CatalogError>> {
+        while !self.done {
+            self.buf.clear();
+            let start = match self.reader.read_event_into(&mut self.buf) {
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"components" => {
+                    self.origin = read_attribute(e, "origin");
+                    self.priority = read_attribute(e, "priority")
+                        .and_then(|p| p.trim().parse().ok())
+                        .unwrap_or(0);
+                    continue;
+                }
+                Ok(Event::Start(ref e)) if e.local_name().as_ref() == b"component" => e.to_owned(),
+                Ok(Event::Eof) => {
+                    self.done = true;
+                    return None;
+                }
+                Ok(_) => continue,
+                Err(e) => {
+                    let location = self.location();
+                    return Some(Err(self.xml_error(location, e)));
+                }
+            };
+
+            let location = self.tag_location();
+            let index = self.index;
+            self.index += 1;
+
+            let origin = self.origin.clone();
+            let result = self.read_component(start).map(|xml| RawComponent {
+                index,
+                location,
+                origin,
+                xml,
+            });
+            return Some(result);
+        }
+
+        None
+    }
+}
+
+impl<'a> Iterator for Components<'a> {
+    type Item = Result<AppStream, CatalogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let raw = self.next_raw()?;
+        Some(raw.and_then(|raw| raw.parse(&self.options)))
+    }
+}
+
+/// The XML of a single component, cut out of the catalog.
+struct RawComponent {
+    index: usize,
+    location: Location,
+    origin: Option<String>,
+    xml: String,
+}
+
+impl RawComponent {
+    fn parse(self, options: &ParseOptions) -> Result<AppStream, CatalogError> {
+        let RawComponent {
+            index,
+            location,
+            origin,
+            xml,
+        } = self;
+
+        let mut component = Metainfo::from_str(xml)
+            .with_options(options.clone())
+            .validate()
+            .map_err(|error| CatalogError::Component {
+                index,
+                location,
+                error,
+            })?;
+        component.set_origin(origin);
+        Ok(component)
+    }
+}
+
+/// Wraps a reader to keep track of the location of what has been read so far.
+struct Tracked<R> {
+    inner: R,
+    position: Position,
+}
+
+/// The location of the next unread byte, and of the last `<` read.
+struct Position {
+    next: Location,
+    last_tag: Location,
+}
+
+impl Position {
+    fn advance(&mut self, mut bytes: &[u8]) {
+        while let Some(i) = bytes.iter().position(|&b| b == b'<') {
+            self.next.advance_bytes(&bytes[..i]);
+            self.last_tag = self.next;
+            self.next.advance_bytes(&bytes[i..=i]);
+            bytes = &bytes[i + 1..];
+        }
+        self.next.advance_bytes(bytes);
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position {
+            next: Location::new(1, 1),
+            last_tag: Location::new(1, 1),
+        }
+    }
+}
+
+impl<R: BufRead> Read for Tracked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position.advance(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Tracked<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Ok(buf) = self.inner.fill_buf() {
+            self.position.advance(&buf[..amt.min(buf.len())]);
+        }
+        self.inner.consume(amt);
+    }
+}
+
+fn read_attribute(element: &BytesStart, name: &str) -> Option<String> {
+    let attr = element.try_get_attribute(name).ok()??;
+    attr.unescape_value().ok().map(|s| s.into_owned())
+}
+
+#[derive(Debug, Fail)]
+pub enum CatalogError {
+    #[fail(display = "Failed to read catalog: {}", _0)]
+    Io(#[cause] io::Error),
+    #[fail(display = "XML error at {}: {}", location, message)]
+    Xml { location: Location, message: String },
+    #[fail(display = "Component {} at {} is invalid: {}", index, location, error)]
+    Component {
+        index: usize,
+        location: Location,
+        #[cause]
+        error: ParseError,
+    },
+}
+
+impl From<io::Error> for CatalogError {
+    fn from(e: io::Error) -> Self {
+        CatalogError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    const CATALOG: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<components version="0.14" origin="fedora" priority="5">
+  <component type="desktop-application">
+    <id>org.foo.bar</id>
+    <name>Bar</name>
+    <summary>Does something amazing</summary>
+    <pkgname>bar</pkgname>
+  </component>
+  <component type="desktop-application">
+    <id>org.foo.baz</id>
+    <name>Baz</name>
+    <summary>Does something else</summary>
+    <pkgname>baz</pkgname>
+  </component>
+</components>
+"#;
+
+    #[test]
+    fn read_compressed_catalog() {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(CATALOG.as_bytes()).unwrap();
+        let data = gz.finish().unwrap();
+
+        let catalog = Catalog::from_reader(data.as_slice()).unwrap();
+        assert_eq!(catalog.origin(), Some("fedora"));
+        assert_eq!(catalog.priority(), 5);
+
+        let ids: Vec<_> = catalog
+            .components()
+            .iter()
+            .map(|c| c.id().to_string())
+            .collect();
+        assert_eq!(ids, vec!["org.foo.bar", "org.foo.baz"]);
+        assert_eq!(catalog.components()[0].origin(), Some("fedora"));
+    }
+
+    #[test]
+    fn invalid_component_location() {
+        let xml = CATALOG.replace("org.foo.baz", "org.foo.b$z");
+        match Catalog::parse(&xml) {
+            Err(CatalogError::Component {
+                index, location, ..
+            }) => {
+                assert_eq!(index, 1);
+                assert_eq!(location, Location::new(9, 3));
+            }
+            other => panic!("expected component error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tld_policy() {
+        let xml = CATALOG.replace("org.foo.baz", "zzz.foo.baz");
+        assert!(Catalog::parse(&xml).is_err());
+
+        let stream = Components::new(xml.as_bytes())
+            .unwrap()
+            .with_tld_policy(TldPolicy::custom(["org", "zzz"]));
+        let catalog = Catalog::from_components(stream).unwrap();
+        assert_eq!(catalog.components()[1].id().to_string(), "zzz.foo.baz");
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn model_types_are_send_and_sync() {
+        assert_send_sync::<AppStream>();
+        assert_send_sync::<::field::id::Id>();
+        assert_send_sync::<::field::license::License>();
+        assert_send_sync::<Catalog>();
+        assert_send_sync::<CatalogError>();
+    }
+
+    #[test]
+    fn stream_skips_invalid_components() {
+        let xml = CATALOG.replace("org.foo.bar", "org.foo.b$r");
+        let results: Vec<_> = Components::new(xml.as_bytes()).unwrap().collect();
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_err());
+        assert_eq!(results[1].as_ref().unwrap().id().to_string(), "org.foo.baz");
+    }
+
+    #[test]
+    fn stream_stops_at_malformed_xml() {
+        let xml = CATALOG.replace("</summary>\n    <pkgname>baz", "\n    <pkgname>baz");
+        let mut stream = Components::new(xml.as_bytes()).unwrap();
+
+        assert!(stream.next().unwrap().is_ok());
+        match stream.next() {
+            Some(Err(CatalogError::Xml { .. })) => {}
+            other => panic!("expected XML error, got {:?}", other),
+        }
+        assert!(stream.next().is_none());
+    }
+}
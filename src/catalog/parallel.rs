@@ -0,0 +1,108 @@
+//! Parses catalog components on several threads, behind the `parallel` feature.
+
+use std::collections::VecDeque;
+
+use rayon::prelude::*;
+
+use super::{CatalogError, Components, RawComponent};
+use AppStream;
+
+/// Number of components which are read ahead and then parsed together.
+const BATCH_SIZE: usize = 256;
+
+impl<'a> Components<'a> {
+    /// Parses the remaining components on the rayon thread pool.
+    ///
+    /// The XML is still read sequentially, and results are yielded in document order.
+    pub fn parallel(self) -> ParComponents<'a> {
+        ParComponents {
+            inner: self,
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+/// An iterator which parses batches of components in parallel, see `Components::parallel`.
+pub struct ParComponents<'a> {
+    inner: Components<'a>,
+    ready: VecDeque<Result<AppStream, CatalogError>>,
+}
+
+impl<'a> ParComponents<'a> {
+    /// Returns the `origin` attribute of the catalog, once the root element has been read.
+    pub fn origin(&self) -> Option<&str> {
+        self.inner.origin()
+    }
+
+    /// Returns the `priority` attribute of the catalog, once the root element has been read.
+    pub fn priority(&self) -> i32 {
+        self.inner.priority()
+    }
+
+    fn fill(&mut self) {
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        while batch.len() < BATCH_SIZE {
+            match self.inner.next_raw() {
+                Some(raw) => batch.push(raw),
+                None => break,
+            }
+        }
+
+        let options = &self.inner.options;
+        let parsed: Vec<_> = batch
+            .into_par_iter()
+            .map(|raw| raw.and_then(|raw| raw.parse(options)))
+            .collect();
+        self.ready.extend(parsed);
+    }
+}
+
+impl<'a> Iterator for ParComponents<'a> {
+    type Item = Result<AppStream, CatalogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ready.is_empty() {
+            self.fill();
+        }
+        self.ready.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_keep_document_order() {
+        let mut xml = String::from("<components origin=\"test\">");
+        for n in 0..(BATCH_SIZE + 10) {
+            let id = if n % 7 == 0 {
+                "b$d".to_string()
+            } else {
+                format!("app{}", n)
+            };
+            xml.push_str(&format!(
+                "<component><id>org.example.{}</id><name>App</name>\
+                 <summary>Does things</summary><pkgname>app</pkgname></component>",
+                id
+            ));
+        }
+        xml.push_str("</components>");
+
+        let results: Vec<_> = Components::new(xml.as_bytes())
+            .unwrap()
+            .parallel()
+            .collect();
+        assert_eq!(results.len(), BATCH_SIZE + 10);
+        for (n, result) in results.iter().enumerate() {
+            match *result {
+                Ok(ref component) => assert_eq!(component.id().app_name(), format!("app{}", n)),
+                Err(CatalogError::Component { index, .. }) => {
+                    assert_eq!(n % 7, 0);
+                    assert_eq!(index, n);
+                }
+                Err(ref e) => panic!("unexpected error {}", e),
+            }
+        }
+    }
+}
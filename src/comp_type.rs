@@ -3,8 +3,12 @@
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::str::FromStr;
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+use field::Field;
+use validate::Diagnose;
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum ComponentType {
+    Generic,
     DesktopApp,
     ConsoleApp,
     WebApp,
@@ -23,6 +27,7 @@ impl FromStr for ComponentType {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
+            "generic" => Ok(ComponentType::Generic),
             "desktop" | "desktop-application" => Ok(ComponentType::DesktopApp),
             "console-application" => Ok(ComponentType::ConsoleApp),
             "web-application" => Ok(ComponentType::WebApp),
@@ -42,6 +47,7 @@ impl FromStr for ComponentType {
 impl Display for ComponentType {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
         match *self {
+            ComponentType::Generic => fmt.write_str("generic"),
             ComponentType::DesktopApp => fmt.write_str("desktop-application"),
             ComponentType::ConsoleApp => fmt.write_str("console-application"),
             ComponentType::WebApp => fmt.write_str("web-application"),
@@ -57,6 +63,32 @@ impl Display for ComponentType {
     }
 }
 
+impl Field for ComponentType {
+    type Input = Option<String>;
+    type Error = InvalidComponentType;
+
+    const XPATH_EXPR: &'static str = "/component/@type";
+
+    /// Components without a `type` attribute are `generic`.
+    fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        match input {
+            Some(s) => ComponentType::from_str(s.trim()),
+            None => Ok(ComponentType::Generic),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, Fail, PartialEq)]
 #[fail(display = "Invalid component `type` attribute: {}", _0)]
 pub struct InvalidComponentType(String);
+
+impl Diagnose for InvalidComponentType {
+    fn tag(&self) -> &'static str {
+        "component-type-invalid"
+    }
+
+    fn subject(&self) -> Option<&str> {
+        let InvalidComponentType(ref kind) = *self;
+        Some(kind)
+    }
+}
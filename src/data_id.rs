@@ -0,0 +1,169 @@
+//! Data IDs, which identify a component from a particular source, e.g.
+//! `system/flatpak/flathub/org.gnome.Maps/stable`.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use field::bundle::BundleKind;
+use AppStream;
+
+/// Matches any value in a data ID segment.
+pub const WILDCARD: &str = "*";
+
+const SEGMENTS: usize = 5;
+
+/// A `scope/bundle-kind/origin/id/branch` data ID, where any segment may be `*`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DataId {
+    scope: String,
+    bundle_kind: String,
+    origin: String,
+    id: String,
+    branch: String,
+}
+
+impl DataId {
+    pub fn new<S: Into<String>>(scope: S, bundle_kind: S, origin: S, id: S, branch: S) -> Self {
+        DataId {
+            scope: scope.into(),
+            bundle_kind: bundle_kind.into(),
+            origin: origin.into(),
+            id: id.into(),
+            branch: branch.into(),
+        }
+    }
+
+    /// Derives the data ID of a component in `scope`, e.g. `system` or `user`.
+    ///
+    /// The bundle kind and branch come from the first `<bundle>`, where the branch is the last
+    /// part of a flatpak ref. Components without bundles are distribution packages if they have
+    /// a `<pkgname>`. Unknown segments are `*`.
+    pub fn from_component(component: &AppStream, scope: &str) -> Self {
+        let bundle = component.bundles().and_then(|bundles| bundles.first());
+        let (bundle_kind, branch) = match bundle {
+            Some(bundle) => {
+                let branch = match *bundle.kind() {
+                    BundleKind::Flatpak => bundle.id().split('/').nth(3),
+                    _ => None,
+                };
+                (bundle.kind().to_string(), branch.unwrap_or(WILDCARD))
+            }
+            None if component.pkg_names().is_some() => (BundleKind::Package.to_string(), WILDCARD),
+            None => (WILDCARD.to_string(), WILDCARD),
+        };
+
+        DataId {
+            scope: scope.to_string(),
+            bundle_kind,
+            origin: component.origin().unwrap_or(WILDCARD).to_string(),
+            id: component.id().to_string(),
+            branch: branch.to_string(),
+        }
+    }
+
+    pub fn scope(&self) -> &str {
+        &self.scope
+    }
+
+    pub fn bundle_kind(&self) -> &str {
+        &self.bundle_kind
+    }
+
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn branch(&self) -> &str {
+        &self.branch
+    }
+
+    /// Compares segment by segment, where `*` on either side matches anything.
+    pub fn matches(&self, other: &DataId) -> bool {
+        self.segments()
+            .iter()
+            .zip(other.segments().iter())
+            .all(|(a, b)| a == b || *a == WILDCARD || *b == WILDCARD)
+    }
+
+    fn segments(&self) -> [&str; SEGMENTS] {
+        [
+            &self.scope,
+            &self.bundle_kind,
+            &self.origin,
+            &self.id,
+            &self.branch,
+        ]
+    }
+}
+
+impl FromStr for DataId {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let segments: Vec<&str> = s.trim().split('/').collect();
+        if segments.len() != SEGMENTS {
+            return Err(ParseError::WrongNumSegments(segments.len()));
+        }
+        if let Some(i) = segments.iter().position(|s| s.is_empty()) {
+            return Err(ParseError::EmptySegment(i + 1));
+        }
+
+        Ok(DataId::new(
+            segments[0],
+            segments[1],
+            segments[2],
+            segments[3],
+            segments[4],
+        ))
+    }
+}
+
+impl Display for DataId {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        fmt.write_str(&self.segments().join("/"))
+    }
+}
+
+#[derive(Clone, Debug, Fail)]
+pub enum ParseError {
+    #[fail(display = "Expected five segments separated by `/`, found {}", _0)]
+    WrongNumSegments(usize),
+    #[fail(display = "Segment {} is empty", _0)]
+    EmptySegment(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_match() {
+        let id: DataId = "system/flatpak/flathub/org.gnome.Maps/stable"
+            .parse()
+            .unwrap();
+        assert_eq!(id.origin(), "flathub");
+        assert_eq!(
+            id.to_string(),
+            "system/flatpak/flathub/org.gnome.Maps/stable"
+        );
+
+        let pattern: DataId = "*/flatpak/*/org.gnome.Maps/*".parse().unwrap();
+        assert!(pattern.matches(&id));
+        assert!(id.matches(&pattern));
+        assert!(!"*/package/*/org.gnome.Maps/*"
+            .parse::<DataId>()
+            .unwrap()
+            .matches(&id));
+
+        assert!("system/flatpak/org.gnome.Maps/stable"
+            .parse::<DataId>()
+            .is_err());
+        assert!("system//flathub/org.gnome.Maps/stable"
+            .parse::<DataId>()
+            .is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Privacy policies, EULAs and other agreements shown to the user.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use xpath_reader::{Error as XpathError, FromXml, Reader};
+
+use super::localized::Localized;
+use super::Field;
+use validate::Diagnose;
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum AgreementKind {
+    Generic,
+    Eula,
+    Privacy,
+}
+
+impl FromStr for AgreementKind {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "generic" => Ok(AgreementKind::Generic),
+            "eula" => Ok(AgreementKind::Eula),
+            "privacy" => Ok(AgreementKind::Privacy),
+            _ => Err(ParseError::InvalidType(s.to_string())),
+        }
+    }
+}
+
+impl Display for AgreementKind {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            AgreementKind::Generic => fmt.write_str("generic"),
+            AgreementKind::Eula => fmt.write_str("eula"),
+            AgreementKind::Privacy => fmt.write_str("privacy"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Agreement {
+    kind: AgreementKind,
+    version_id: Option<String>,
+    sections: Vec<AgreementSection>,
+}
+
+impl Agreement {
+    pub fn kind(&self) -> &AgreementKind {
+        &self.kind
+    }
+
+    pub fn version_id(&self) -> Option<&str> {
+        self.version_id.as_deref()
+    }
+
+    pub fn sections(&self) -> &[AgreementSection] {
+        self.sections.as_slice()
+    }
+
+    /// Returns the `type` of every section, in document order.
+    pub fn section_kinds(&self) -> Vec<&str> {
+        self.sections.iter().filter_map(|s| s.kind()).collect()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct AgreementSection {
+    kind: Option<String>,
+    name: Localized,
+    description: Localized,
+}
+
+impl AgreementSection {
+    /// Returns the section type, e.g. `GDPR-data-collection`.
+    pub fn kind(&self) -> Option<&str> {
+        self.kind.as_deref()
+    }
+
+    pub fn name(&self) -> &Localized {
+        &self.name
+    }
+
+    pub fn description(&self) -> &Localized {
+        &self.description
+    }
+}
+
+impl FromXml for AgreementSection {
+    fn from_xml<'d>(reader: &'d Reader<'d>) -> Result<Self, XpathError> {
+        Ok(AgreementSection {
+            kind: reader.read("@type")?,
+            name: reader.read("name")?,
+            description: reader.read("description")?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct AgreementInput {
+    kind: Option<String>,
+    version_id: Option<String>,
+    sections: Vec<AgreementSection>,
+}
+
+impl FromXml for AgreementInput {
+    fn from_xml<'d>(reader: &'d Reader<'d>) -> Result<Self, XpathError> {
+        Ok(AgreementInput {
+            kind: reader.read("@type")?,
+            version_id: reader.read("@version_id")?,
+            sections: reader.read("agreement_section")?,
+        })
+    }
+}
+
+impl Field for Option<Vec<Agreement>> {
+    type Input = Vec<AgreementInput>;
+    type Error = ParseError;
+
+    const XPATH_EXPR: &'static str = "/component/agreement";
+
+    fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        input
+            .into_iter()
+            .map(|agreement| {
+                let kind = match agreement.kind {
+                    Some(ref kind) => AgreementKind::from_str(kind)?,
+                    None => AgreementKind::Generic,
+                };
+
+                Ok(Agreement {
+                    kind,
+                    version_id: agreement.version_id,
+                    sections: agreement.sections,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+}
+
+#[derive(Clone, Debug, Fail)]
+pub enum ParseError {
+    #[fail(display = "Invalid agreement type `{}`", _0)]
+    InvalidType(String),
+}
+
+impl Diagnose for ParseError {
+    fn tag(&self) -> &'static str {
+        match *self {
+            ParseError::InvalidType(_) => "agreement-type-invalid",
+        }
+    }
+
+    fn subject(&self) -> Option<&str> {
+        match *self {
+            ParseError::InvalidType(ref kind) => Some(kind),
+        }
+    }
+}
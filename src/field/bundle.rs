@@ -0,0 +1,137 @@
+//! Bundles which ship a component outside of distribution packages.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use xpath_reader::{Error as XpathError, FromXml, Reader};
+
+use super::Field;
+use validate::Diagnose;
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum BundleKind {
+    Package,
+    Limba,
+    Flatpak,
+    AppImage,
+    Snap,
+    Tarball,
+    Cabinet,
+}
+
+impl FromStr for BundleKind {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "package" => Ok(BundleKind::Package),
+            "limba" => Ok(BundleKind::Limba),
+            "flatpak" => Ok(BundleKind::Flatpak),
+            "appimage" => Ok(BundleKind::AppImage),
+            "snap" => Ok(BundleKind::Snap),
+            "tarball" => Ok(BundleKind::Tarball),
+            "cabinet" => Ok(BundleKind::Cabinet),
+            _ => Err(ParseError::InvalidType(s.to_string())),
+        }
+    }
+}
+
+impl Display for BundleKind {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            BundleKind::Package => fmt.write_str("package"),
+            BundleKind::Limba => fmt.write_str("limba"),
+            BundleKind::Flatpak => fmt.write_str("flatpak"),
+            BundleKind::AppImage => fmt.write_str("appimage"),
+            BundleKind::Snap => fmt.write_str("snap"),
+            BundleKind::Tarball => fmt.write_str("tarball"),
+            BundleKind::Cabinet => fmt.write_str("cabinet"),
+        }
+    }
+}
+
+/// A bundle reference, e.g. a flatpak ref such as `app/org.gnome.Maps/x86_64/stable`.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Bundle {
+    kind: BundleKind,
+    id: String,
+}
+
+impl Bundle {
+    pub fn new<S: Into<String>>(kind: BundleKind, id: S) -> Self {
+        Bundle {
+            kind,
+            id: id.into(),
+        }
+    }
+
+    pub fn kind(&self) -> &BundleKind {
+        &self.kind
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[derive(Debug)]
+pub struct BundleInput {
+    kind: Option<String>,
+    id: String,
+}
+
+impl FromXml for BundleInput {
+    fn from_xml<'d>(reader: &'d Reader<'d>) -> Result<Self, XpathError> {
+        Ok(BundleInput {
+            kind: reader.read("@type")?,
+            id: reader.read(".")?,
+        })
+    }
+}
+
+impl Field for Option<Vec<Bundle>> {
+    type Input = Vec<BundleInput>;
+    type Error = ParseError;
+
+    const XPATH_EXPR: &'static str = "/component/bundle";
+
+    fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        input
+            .into_iter()
+            .map(|bundle| {
+                let kind = bundle.kind.ok_or(ParseError::MissingType)?;
+                let id = bundle.id.trim();
+                if id.is_empty() {
+                    return Err(ParseError::MissingId);
+                }
+
+                Ok(Bundle::new(BundleKind::from_str(&kind)?, id))
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+}
+
+#[derive(Clone, Debug, Fail)]
+pub enum ParseError {
+    #[fail(display = "Missing bundle `type` attribute")]
+    MissingType,
+    #[fail(display = "Invalid bundle type `{}`", _0)]
+    InvalidType(String),
+    #[fail(display = "Empty bundle ID")]
+    MissingId,
+}
+
+impl Diagnose for ParseError {
+    fn tag(&self) -> &'static str {
+        match *self {
+            ParseError::MissingType => "bundle-type-missing",
+            ParseError::InvalidType(_) => "bundle-type-invalid",
+            ParseError::MissingId => "bundle-id-missing",
+        }
+    }
+}
@@ -1,12 +1,21 @@
-use std::collections::HashMap;
+//! Categories from the freedesktop.org Desktop Menu Specification registry.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::ops::Deref;
 use std::str::FromStr;
 
+use super::localized::lookup;
 use super::Field;
+use validate::Diagnose;
 
-const CATEGORIES: &[&str] = &[
-    "Audio",
+/// Prefix for vendor-specific categories not present in the registry.
+const VENDOR_PREFIX: &str = "X-";
+
+const MAIN_CATEGORIES: &[&str] = &[
     "AudioVideo",
+    "Audio",
+    "Video",
     "Development",
     "Education",
     "Game",
@@ -17,38 +26,457 @@ const CATEGORIES: &[&str] = &[
     "Settings",
     "System",
     "Utility",
-    "Video",
 ];
 
+const ADDITIONAL_CATEGORIES: &[&str] = &[
+    "Building",
+    "Debugger",
+    "IDE",
+    "GUIDesigner",
+    "Profiling",
+    "RevisionControl",
+    "Translation",
+    "Calendar",
+    "ContactManagement",
+    "Database",
+    "Dictionary",
+    "Chart",
+    "Email",
+    "Finance",
+    "FlowChart",
+    "PDA",
+    "ProjectManagement",
+    "Presentation",
+    "Spreadsheet",
+    "WordProcessor",
+    "2DGraphics",
+    "VectorGraphics",
+    "RasterGraphics",
+    "3DGraphics",
+    "Scanning",
+    "OCR",
+    "Photography",
+    "Publishing",
+    "Viewer",
+    "TextTools",
+    "DesktopSettings",
+    "HardwareSettings",
+    "Printing",
+    "PackageManager",
+    "Dialup",
+    "InstantMessaging",
+    "Chat",
+    "IRCClient",
+    "Feed",
+    "FileTransfer",
+    "HamRadio",
+    "News",
+    "P2P",
+    "RemoteAccess",
+    "Telephony",
+    "TelephonyTools",
+    "VideoConference",
+    "WebBrowser",
+    "WebDevelopment",
+    "Midi",
+    "Mixer",
+    "Sequencer",
+    "Tuner",
+    "TV",
+    "AudioVideoEditing",
+    "Player",
+    "Recorder",
+    "DiscBurning",
+    "ActionGame",
+    "AdventureGame",
+    "ArcadeGame",
+    "BoardGame",
+    "BlocksGame",
+    "CardGame",
+    "KidsGame",
+    "LogicGame",
+    "RolePlaying",
+    "Shooter",
+    "Simulation",
+    "SportsGame",
+    "StrategyGame",
+    "Art",
+    "Construction",
+    "Music",
+    "Languages",
+    "ArtificialIntelligence",
+    "Astronomy",
+    "Biology",
+    "Chemistry",
+    "ComputerScience",
+    "DataVisualization",
+    "Economy",
+    "Electricity",
+    "Geography",
+    "Geology",
+    "Geoscience",
+    "History",
+    "Humanities",
+    "ImageProcessing",
+    "Literature",
+    "Maps",
+    "Math",
+    "NumericalAnalysis",
+    "MedicalSoftware",
+    "Physics",
+    "Robotics",
+    "Spirituality",
+    "Sports",
+    "ParallelComputing",
+    "Amusement",
+    "Archiving",
+    "Compression",
+    "Electronics",
+    "Emulator",
+    "Engineering",
+    "FileTools",
+    "FileManager",
+    "TerminalEmulator",
+    "Filesystem",
+    "Monitor",
+    "Security",
+    "Accessibility",
+    "Calculator",
+    "Clock",
+    "TextEditor",
+    "Documentation",
+    "Adult",
+    "Core",
+    "KDE",
+    "GNOME",
+    "XFCE",
+    "DDE",
+    "GTK",
+    "Qt",
+    "Motif",
+    "Java",
+    "ConsoleOnly",
+];
+
+const RESERVED_CATEGORIES: &[&str] = &["Screensaver", "TrayIcon", "Applet", "Shell"];
+
 lazy_static! {
+    /// Untranslated menu labels for the main categories, as used by desktop menus.
+    static ref MAIN_CATEGORY_LABELS: HashMap<&'static str, &'static str> = {
+        let mut map = HashMap::new();
+        map.insert("AudioVideo", "Sound & Video");
+        map.insert("Audio", "Audio");
+        map.insert("Video", "Video");
+        map.insert("Development", "Programming");
+        map.insert("Education", "Education");
+        map.insert("Game", "Games");
+        map.insert("Graphics", "Graphics");
+        map.insert("Network", "Internet");
+        map.insert("Office", "Office");
+        map.insert("Science", "Science");
+        map.insert("Settings", "Settings");
+        map.insert("System", "System Tools");
+        map.insert("Utility", "Accessories");
+        map
+    };
+
+    /// Translations of the main category labels, keyed by language and then by category.
+    static ref TRANSLATED_LABELS: BTreeMap<String, HashMap<&'static str, &'static str>> = {
+        let table: &[(&str, &[(&str, &str)])] = &[
+            ("de", &[
+                ("AudioVideo", "Multimedia"),
+                ("Audio", "Audio"),
+                ("Video", "Video"),
+                ("Development", "Programmierung"),
+                ("Education", "Bildung"),
+                ("Game", "Spiele"),
+                ("Graphics", "Grafik"),
+                ("Network", "Internet"),
+                ("Office", "Büro"),
+                ("Science", "Wissenschaft"),
+                ("Settings", "Einstellungen"),
+                ("System", "Systemwerkzeuge"),
+                ("Utility", "Zubehör"),
+            ]),
+            ("es", &[
+                ("AudioVideo", "Sonido y vídeo"),
+                ("Audio", "Audio"),
+                ("Video", "Vídeo"),
+                ("Development", "Programación"),
+                ("Education", "Educación"),
+                ("Game", "Juegos"),
+                ("Graphics", "Gráficos"),
+                ("Network", "Internet"),
+                ("Office", "Oficina"),
+                ("Science", "Ciencia"),
+                ("Settings", "Configuración"),
+                ("System", "Herramientas del sistema"),
+                ("Utility", "Accesorios"),
+            ]),
+            ("fr", &[
+                ("AudioVideo", "Son et vidéo"),
+                ("Audio", "Audio"),
+                ("Video", "Vidéo"),
+                ("Development", "Programmation"),
+                ("Education", "Éducation"),
+                ("Game", "Jeux"),
+                ("Graphics", "Graphisme"),
+                ("Network", "Internet"),
+                ("Office", "Bureautique"),
+                ("Science", "Science"),
+                ("Settings", "Paramètres"),
+                ("System", "Outils système"),
+                ("Utility", "Accessoires"),
+            ]),
+        ];
+
+        table
+            .iter()
+            .map(|&(language, labels)| (language.to_string(), labels.iter().cloned().collect()))
+            .collect()
+    };
+
+    /// Main categories that an additional category must appear together with (any one of).
+    ///
+    /// Additional categories without an entry may be used on their own.
     static ref RELATED_CATEGORIES: HashMap<&'static str, Vec<&'static str>> = {
         let mut map = HashMap::new();
+        map.insert("Audio", vec!["AudioVideo"]);
+        map.insert("Video", vec!["AudioVideo"]);
         map.insert("Building", vec!["Development"]);
         map.insert("Debugger", vec!["Development"]);
         map.insert("IDE", vec!["Development"]);
+        map.insert("GUIDesigner", vec!["Development"]);
+        map.insert("Profiling", vec!["Development"]);
+        map.insert("RevisionControl", vec!["Development"]);
+        map.insert("Translation", vec!["Development"]);
+        map.insert("Calendar", vec!["Office"]);
+        map.insert("ContactManagement", vec!["Office"]);
+        map.insert("Database", vec!["Office", "Development", "AudioVideo"]);
+        map.insert("Dictionary", vec!["Office", "Utility"]);
+        map.insert("Chart", vec!["Office"]);
+        map.insert("Email", vec!["Office", "Network"]);
+        map.insert("Finance", vec!["Office"]);
+        map.insert("FlowChart", vec!["Office"]);
+        map.insert("PDA", vec!["Office"]);
+        map.insert("ProjectManagement", vec!["Office", "Development"]);
+        map.insert("Presentation", vec!["Office"]);
+        map.insert("Spreadsheet", vec!["Office"]);
+        map.insert("WordProcessor", vec!["Office"]);
+        map.insert("2DGraphics", vec!["Graphics"]);
+        map.insert("VectorGraphics", vec!["Graphics"]);
+        map.insert("RasterGraphics", vec!["Graphics"]);
+        map.insert("3DGraphics", vec!["Graphics"]);
+        map.insert("Scanning", vec!["Graphics"]);
+        map.insert("OCR", vec!["Graphics"]);
+        map.insert("Photography", vec!["Graphics", "Office"]);
+        map.insert("Publishing", vec!["Graphics", "Office"]);
+        map.insert("Viewer", vec!["Graphics", "Office"]);
+        map.insert("TextTools", vec!["Utility"]);
+        map.insert("DesktopSettings", vec!["Settings"]);
+        map.insert("HardwareSettings", vec!["Settings"]);
+        map.insert("Printing", vec!["Settings"]);
+        map.insert("PackageManager", vec!["Settings"]);
+        map.insert("Dialup", vec!["Network"]);
+        map.insert("InstantMessaging", vec!["Network"]);
+        map.insert("Chat", vec!["Network"]);
+        map.insert("IRCClient", vec!["Network"]);
+        map.insert("Feed", vec!["Network"]);
+        map.insert("FileTransfer", vec!["Network"]);
+        map.insert("HamRadio", vec!["Network", "Audio"]);
+        map.insert("News", vec!["Network"]);
+        map.insert("P2P", vec!["Network"]);
+        map.insert("RemoteAccess", vec!["Network"]);
+        map.insert("Telephony", vec!["Network"]);
+        map.insert("TelephonyTools", vec!["Utility"]);
+        map.insert("VideoConference", vec!["Network"]);
+        map.insert("WebBrowser", vec!["Network"]);
+        map.insert("WebDevelopment", vec!["Network", "Development"]);
+        map.insert("Midi", vec!["AudioVideo", "Audio"]);
+        map.insert("Mixer", vec!["AudioVideo", "Audio"]);
+        map.insert("Sequencer", vec!["AudioVideo", "Audio"]);
+        map.insert("Tuner", vec!["AudioVideo", "Audio"]);
+        map.insert("TV", vec!["AudioVideo", "Video"]);
+        map.insert("AudioVideoEditing", vec!["Audio", "Video", "AudioVideo"]);
+        map.insert("Player", vec!["Audio", "Video", "AudioVideo"]);
+        map.insert("Recorder", vec!["Audio", "Video", "AudioVideo"]);
+        map.insert("DiscBurning", vec!["AudioVideo"]);
+        map.insert("ActionGame", vec!["Game"]);
+        map.insert("AdventureGame", vec!["Game"]);
+        map.insert("ArcadeGame", vec!["Game"]);
+        map.insert("BoardGame", vec!["Game"]);
+        map.insert("BlocksGame", vec!["Game"]);
+        map.insert("CardGame", vec!["Game"]);
+        map.insert("KidsGame", vec!["Game"]);
+        map.insert("LogicGame", vec!["Game"]);
+        map.insert("RolePlaying", vec!["Game"]);
+        map.insert("Shooter", vec!["Game"]);
+        map.insert("Simulation", vec!["Game"]);
+        map.insert("SportsGame", vec!["Game"]);
+        map.insert("StrategyGame", vec!["Game"]);
+        map.insert("Art", vec!["Education", "Science"]);
+        map.insert("Construction", vec!["Education", "Science"]);
+        map.insert("Music", vec!["AudioVideo", "Education"]);
+        map.insert("Languages", vec!["Education", "Science"]);
+        map.insert("ArtificialIntelligence", vec!["Education", "Science"]);
+        map.insert("Astronomy", vec!["Education", "Science"]);
+        map.insert("Biology", vec!["Education", "Science"]);
+        map.insert("Chemistry", vec!["Education", "Science"]);
+        map.insert("ComputerScience", vec!["Education", "Science"]);
+        map.insert("DataVisualization", vec!["Education", "Science"]);
+        map.insert("Economy", vec!["Education", "Science"]);
+        map.insert("Electricity", vec!["Education", "Science"]);
+        map.insert("Geography", vec!["Education", "Science"]);
+        map.insert("Geology", vec!["Education", "Science"]);
+        map.insert("Geoscience", vec!["Education", "Science"]);
+        map.insert("History", vec!["Education", "Science"]);
+        map.insert("Humanities", vec!["Education", "Science"]);
+        map.insert("ImageProcessing", vec!["Education", "Science"]);
+        map.insert("Literature", vec!["Education", "Science"]);
+        map.insert("Maps", vec!["Education", "Science", "Utility"]);
+        map.insert("Math", vec!["Education", "Science"]);
+        map.insert("NumericalAnalysis", vec!["Education", "Science"]);
+        map.insert("MedicalSoftware", vec!["Education", "Science"]);
+        map.insert("Physics", vec!["Education", "Science"]);
+        map.insert("Robotics", vec!["Education", "Science"]);
+        map.insert("Spirituality", vec!["Education", "Science", "Utility"]);
+        map.insert("Sports", vec!["Education", "Science"]);
+        map.insert("ParallelComputing", vec!["Education", "Science"]);
+        map.insert("Archiving", vec!["Utility"]);
+        map.insert("Compression", vec!["Utility"]);
+        map.insert("Emulator", vec!["System", "Game"]);
+        map.insert("FileTools", vec!["Utility", "System"]);
+        map.insert("FileManager", vec!["System"]);
+        map.insert("TerminalEmulator", vec!["System"]);
+        map.insert("Filesystem", vec!["System"]);
+        map.insert("Monitor", vec!["System", "Network"]);
+        map.insert("Security", vec!["Settings", "System"]);
+        map.insert("Accessibility", vec!["Settings", "Utility"]);
+        map.insert("Calculator", vec!["Utility"]);
+        map.insert("Clock", vec!["Utility"]);
+        map.insert("TextEditor", vec!["Utility"]);
         map
     };
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// Where a category comes from in the Desktop Menu Specification.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CategoryKind {
+    Main,
+    Additional,
+    Reserved,
+    /// A vendor extension prefixed with `X-`, which is accepted but discouraged.
+    Vendor,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Category(String);
 
+impl Category {
+    pub fn kind(&self) -> CategoryKind {
+        let Category(ref name) = *self;
+        if MAIN_CATEGORIES.contains(&name.as_str()) {
+            CategoryKind::Main
+        } else if ADDITIONAL_CATEGORIES.contains(&name.as_str()) {
+            CategoryKind::Additional
+        } else if RESERVED_CATEGORIES.contains(&name.as_str()) {
+            CategoryKind::Reserved
+        } else {
+            CategoryKind::Vendor
+        }
+    }
+
+    /// Returns the untranslated menu label, falling back to the category name itself.
+    pub fn label(&self) -> &str {
+        let Category(ref name) = *self;
+        MAIN_CATEGORY_LABELS
+            .get(name.as_str())
+            .cloned()
+            .unwrap_or(name.as_str())
+    }
+
+    /// Returns the menu label translated to `locale`, e.g. `de_DE`.
+    ///
+    /// Falls back to the language without territory and then to the untranslated label.
+    pub fn label_for(&self, locale: &str) -> &str {
+        let Category(ref name) = *self;
+        lookup(&TRANSLATED_LABELS, locale)
+            .and_then(|labels| labels.get(name.as_str()))
+            .cloned()
+            .unwrap_or_else(|| self.label())
+    }
+
+    /// Returns the position of this category in the registry, used for menu ordering.
+    pub(crate) fn registry_index(&self) -> usize {
+        let Category(ref name) = *self;
+        MAIN_CATEGORIES
+            .iter()
+            .chain(ADDITIONAL_CATEGORIES)
+            .chain(RESERVED_CATEGORIES)
+            .position(|&cat| cat == name)
+            .unwrap_or(usize::MAX)
+    }
+
+    /// Returns the main categories this category must be listed together with, if any.
+    pub fn related(&self) -> &'static [&'static str] {
+        let Category(ref name) = *self;
+        RELATED_CATEGORIES
+            .get(name.as_str())
+            .map(|related| related.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        let Category(ref name) = *self;
+        name.fmt(fmt)
+    }
+}
+
 impl FromStr for Category {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let cat = CATEGORIES
+        let known = MAIN_CATEGORIES
             .iter()
-            .find(|&cat| s == *cat)
-            .ok_or(ParseError::UnknownCategory(s.into()))?;
+            .chain(ADDITIONAL_CATEGORIES)
+            .chain(RESERVED_CATEGORIES)
+            .any(|&cat| s == cat);
 
-        Ok(Category(cat.to_string()))
+        if known || (s.starts_with(VENDOR_PREFIX) && s.len() > VENDOR_PREFIX.len()) {
+            Ok(Category(s.to_string()))
+        } else {
+            Err(ParseError::UnknownCategory(s.into()))
+        }
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct Categories(Vec<Category>);
 
+impl Categories {
+    /// Returns the `X-` vendor categories, which should be reported as warnings.
+    pub fn vendor_categories(&self) -> Vec<&Category> {
+        self.iter()
+            .filter(|cat| cat.kind() == CategoryKind::Vendor)
+            .collect()
+    }
+
+    /// Adds the categories of `other` which are not listed yet.
+    pub(crate) fn append(&mut self, other: &Categories) {
+        for cat in other.iter() {
+            if !self.contains_name(&cat.0) {
+                self.0.push(cat.clone());
+            }
+        }
+    }
+
+    fn contains_name(&self, name: &str) -> bool {
+        self.iter().any(|cat| cat.0 == name)
+    }
+}
+
 impl Deref for Categories {
     type Target = [Category];
 
@@ -65,15 +493,27 @@ impl Field for Option<Categories> {
     const XPATH_EXPR: &'static str = "/component/categories/category/text()";
 
     fn construct(input: Self::Input) -> Result<Self, Self::Error> {
-        if input.len() == 0 {
+        if input.is_empty() {
             return Ok(None);
         }
 
-        input
+        let categories = input
             .into_iter()
             .map(|value| Category::from_str(&value))
             .collect::<Result<_, _>>()
-            .map(|parsed| Some(Categories(parsed)))
+            .map(Categories)?;
+
+        for cat in categories.iter() {
+            let related = cat.related();
+            if !related.is_empty() && !related.iter().any(|r| categories.contains_name(r)) {
+                return Err(ParseError::MissingRelatedCategory {
+                    category: cat.to_string(),
+                    related: related.join(", "),
+                });
+            }
+        }
+
+        Ok(Some(categories))
     }
 }
 
@@ -81,4 +521,58 @@ impl Field for Option<Categories> {
 pub enum ParseError {
     #[fail(display = "unknown category: {}", _0)]
     UnknownCategory(String),
+    #[fail(
+        display = "category `{}` must be listed together with one of: {}",
+        category,
+        related
+    )]
+    MissingRelatedCategory { category: String, related: String },
+}
+
+impl Diagnose for ParseError {
+    fn tag(&self) -> &'static str {
+        match *self {
+            ParseError::UnknownCategory(_) => "category-invalid",
+            ParseError::MissingRelatedCategory { .. } => "category-missing-related",
+        }
+    }
+
+    fn subject(&self) -> Option<&str> {
+        match *self {
+            ParseError::UnknownCategory(ref category) => Some(category),
+            ParseError::MissingRelatedCategory { ref category, .. } => Some(category),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn construct(cats: &[&str]) -> Result<Option<Categories>, ParseError> {
+        <Option<Categories> as Field>::construct(cats.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn additional_requires_related_main() {
+        assert!(construct(&["Utility", "TextEditor"]).is_ok());
+        assert!(construct(&["AudioVideo", "Audio", "Player"]).is_ok());
+        assert!(construct(&["Audio", "Player"]).is_err());
+        assert!(construct(&["Video"]).is_err());
+
+        match construct(&["TextEditor"]) {
+            Err(ParseError::MissingRelatedCategory { ref category, .. }) => {
+                assert_eq!(category, "TextEditor")
+            }
+            other => panic!("expected missing related category, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn vendor_categories_accepted() {
+        let cats = construct(&["Utility", "X-Foo"]).unwrap().unwrap();
+        assert_eq!(cats.vendor_categories(), vec![&Category("X-Foo".into())]);
+        assert!(construct(&["Bogus"]).is_err());
+        assert!(construct(&["X-"]).is_err());
+    }
 }
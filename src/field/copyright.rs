@@ -1,54 +1,228 @@
+//! Copyright statements and REUSE tags found in comments.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use failure::Fail;
 use regex::Regex;
 
+use super::license::License;
 use super::Field;
+use validate::Diagnose;
+
+/// REUSE tag for a copyright statement, e.g. `SPDX-FileCopyrightText: 2018 Jane Doe`.
+const SPDX_COPYRIGHT_TAG: &str = "SPDX-FileCopyrightText:";
+
+/// REUSE tag for the license of the file, e.g. `SPDX-License-Identifier: CC0-1.0`.
+const SPDX_LICENSE_TAG: &str = "SPDX-License-Identifier:";
+
+/// Prefixes of plain copyright lines. `Copyrighted` or `Copyright:` do not start a statement.
+const COPYRIGHT_PREFIXES: &[&str] = &["Copyright ", "Copyright©"];
 
 lazy_static! {
-    static ref COPYRIGHT_COMMENT: Regex = Regex::new(r"Copyright [0-9]{4}((, |-)[0-9]{4})* .*")
-        .expect("Invalid regular expression for validating copyright comments");
-    static ref DATE_RANGE: Regex = Regex::new(r"[0-9]{4}((, |-)[0-9]{4})*")
+    static ref COPYRIGHT_SYMBOL: Regex = Regex::new(r"^(Copyright\s*)?(\([cC]\)|©)?\s*")
+        .expect("Invalid regular expression for selecting copyright symbols");
+    static ref DATE_RANGE: Regex = Regex::new(r"^[0-9]{4}((, ?|-)[0-9]{4})*")
         .expect("Invalid regular expression for selecting date ranges");
-    static ref HOLDER_TEXT: Regex = Regex::new(r"Copyright [0-9]{4}((, |-)[0-9]{4})* ")
-        .expect("Invalid regular expression for selecting non-holder text");
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Copyright {
-    year: String,
+/// A single copyright line, e.g. `Copyright 2014-2018 Jane Doe <jane@example.org>`.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct CopyrightStatement {
+    years: Option<String>,
     holder: String,
 }
 
-impl Field for Copyright {
-    type Input = String;
+impl CopyrightStatement {
+    /// Returns the year range as written, e.g. `2014-2018` or `2014, 2016`.
+    pub fn years(&self) -> Option<&str> {
+        self.years.as_deref()
+    }
+
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let rest = COPYRIGHT_SYMBOL.replace(text, "");
+        let years = DATE_RANGE.find(&rest).map(|m| m.as_str().to_string());
+        let holder = match years {
+            Some(ref years) => &rest[years.len()..],
+            None => &rest[..],
+        };
+
+        let holder = holder.trim_start_matches(',').trim();
+        if holder.is_empty() {
+            return None;
+        }
+
+        Some(CopyrightStatement {
+            years,
+            holder: holder.to_string(),
+        })
+    }
+}
+
+/// Copyright and licensing information from the comments of a metainfo file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Copyright {
+    statements: Vec<CopyrightStatement>,
+    licenses: Vec<License>,
+    invalid: Vec<String>,
+    invalid_licenses: Vec<String>,
+}
+
+impl Copyright {
+    /// Returns every `Copyright ...` and `SPDX-FileCopyrightText:` line, in document order.
+    pub fn statements(&self) -> &[CopyrightStatement] {
+        self.statements.as_slice()
+    }
+
+    /// Returns the licenses declared with `SPDX-License-Identifier:` tags.
+    pub fn licenses(&self) -> &[License] {
+        self.licenses.as_slice()
+    }
+
+    /// Returns the copyright lines which could not be parsed, which are reported as warnings.
+    pub fn invalid_statements(&self) -> &[String] {
+        self.invalid.as_slice()
+    }
+
+    /// Returns the `SPDX-License-Identifier:` expressions which could not be parsed, which are
+    /// reported as warnings.
+    pub fn invalid_licenses(&self) -> &[String] {
+        self.invalid_licenses.as_slice()
+    }
+}
+
+impl Field for Option<Copyright> {
+    type Input = Vec<String>;
     type Error = ParseError;
 
-    const XPATH_EXPR: &'static str = "/comment()";
+    const XPATH_EXPR: &'static str = "//comment()";
 
     fn construct(input: Self::Input) -> Result<Self, Self::Error> {
-        let trimmed = input.trim();
+        let mut statements = Vec::new();
+        let mut licenses = Vec::new();
+        let mut invalid = Vec::new();
+        let mut invalid_licenses = Vec::new();
 
-        let year = DATE_RANGE
-            .find(trimmed)
-            .map(|s| s.as_str().to_string())
-            .ok_or(ParseError::MalformedDateRange(trimmed.to_string()))?;
+        for line in input.iter().flat_map(|comment| comment.lines()) {
+            let line = line.trim();
+            if let Some(expr) = line.strip_prefix(SPDX_LICENSE_TAG) {
+                match License::new(expr.trim()) {
+                    Ok(license) => licenses.push(license),
+                    Err(_) => invalid_licenses.push(expr.trim().to_string()),
+                }
+                continue;
+            }
 
-        let holder = HOLDER_TEXT.replace(trimmed, "").into_owned();
+            let text = match line.strip_prefix(SPDX_COPYRIGHT_TAG) {
+                Some(text) => text.trim(),
+                None if COPYRIGHT_PREFIXES.iter().any(|p| line.starts_with(p)) => line,
+                None => continue,
+            };
 
-        if !COPYRIGHT_COMMENT.is_match(trimmed) {
-            return Err(ParseError::InvalidCopyright(trimmed.to_string()));
+            match CopyrightStatement::parse(text) {
+                Some(statement) => statements.push(statement),
+                None => invalid.push(line.to_string()),
+            }
         }
 
-        Ok(Copyright { year, holder })
+        if statements.is_empty()
+            && licenses.is_empty()
+            && invalid.is_empty()
+            && invalid_licenses.is_empty()
+        {
+            Ok(None)
+        } else {
+            Ok(Some(Copyright {
+                statements,
+                licenses,
+                invalid,
+                invalid_licenses,
+            }))
+        }
+    }
+}
+
+/// Copyright comments never fail to parse; problems in them are reported as warnings.
+#[derive(Clone, Debug)]
+pub enum ParseError {}
+
+impl Display for ParseError {
+    fn fmt(&self, _fmt: &mut Formatter) -> FmtResult {
+        match *self {}
+    }
+}
+
+impl Fail for ParseError {}
+
+impl Diagnose for ParseError {
+    fn tag(&self) -> &'static str {
+        match *self {}
     }
 }
 
-#[derive(Clone, Debug, Fail)]
-pub enum ParseError {
-    #[fail(
-        display = "Expected copyright comment, e.g. <!-- Copyright YYYY First Last <maintainer@email.org> -->"
-    )]
-    MissingCopyright,
-    #[fail(display = "Malformed date range: {}", _0)]
-    MalformedDateRange(String),
-    #[fail(display = "Invalid copyright comment: {}", _0)]
-    InvalidCopyright(String),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn construct(comments: &[&str]) -> Result<Option<Copyright>, ParseError> {
+        let input = comments.iter().map(|s| s.to_string()).collect();
+        <Option<Copyright> as Field>::construct(input)
+    }
+
+    #[test]
+    fn multiple_statements() {
+        let copyright = construct(&[
+            " Copyright 2014-2018 Jane Doe <jane@example.org> ",
+            "\n SPDX-FileCopyrightText: © 2019, 2021 John Doe\n SPDX-License-Identifier: CC0-1.0\n",
+            " Copyright (C) Example Corp. ",
+        ])
+        .unwrap()
+        .unwrap();
+
+        let statements = copyright.statements();
+        assert_eq!(statements.len(), 3);
+        assert_eq!(statements[0].years(), Some("2014-2018"));
+        assert_eq!(statements[0].holder(), "Jane Doe <jane@example.org>");
+        assert_eq!(statements[1].years(), Some("2019, 2021"));
+        assert_eq!(statements[1].holder(), "John Doe");
+        assert_eq!(statements[2].years(), None);
+        assert_eq!(statements[2].holder(), "Example Corp.");
+        assert_eq!(copyright.licenses()[0].to_string(), "CC0-1.0");
+    }
+
+    #[test]
+    fn optional() {
+        assert_eq!(construct(&[" just a comment "]).unwrap(), None);
+        assert_eq!(
+            construct(&[" Copyrighted material, Copyright: none "]).unwrap(),
+            None
+        );
+
+        let copyright = construct(&[" SPDX-License-Identifier: Bogus "])
+            .unwrap()
+            .unwrap();
+        assert!(copyright.licenses().is_empty());
+        assert_eq!(copyright.invalid_licenses(), ["Bogus"]);
+
+        let copyright = construct(&[" Copyright 2018 "]).unwrap().unwrap();
+        assert!(copyright.statements().is_empty());
+        assert_eq!(copyright.invalid_statements(), ["Copyright 2018"]);
+    }
+
+    #[test]
+    fn copyright_symbol() {
+        let copyright = construct(&[" Copyright © 2018 Jane Doe ", " Copyright©2019 John Doe "])
+            .unwrap()
+            .unwrap();
+
+        let statements = copyright.statements();
+        assert_eq!(statements.len(), 2);
+        assert_eq!(statements[0].years(), Some("2018"));
+        assert_eq!(statements[0].holder(), "Jane Doe");
+        assert_eq!(statements[1].years(), Some("2019"));
+        assert_eq!(statements[1].holder(), "John Doe");
+    }
 }
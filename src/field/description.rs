@@ -0,0 +1,65 @@
+//! The long, translatable `<description>` of a component.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::Deref;
+
+use super::localized::{Localized, LocalizedValue};
+use super::Field;
+use validate::Diagnose;
+
+/// The paragraphs and list items of a `<description>`, as plain text separated by blank lines.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Description(Localized);
+
+impl Description {
+    pub(crate) fn localized_mut(&mut self) -> &mut Localized {
+        let Description(ref mut value) = *self;
+        value
+    }
+}
+
+impl Deref for Description {
+    type Target = Localized;
+
+    fn deref(&self) -> &Localized {
+        let Description(ref description) = *self;
+        description
+    }
+}
+
+impl Display for Description {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        self.default_value().unwrap_or("").fmt(fmt)
+    }
+}
+
+impl Field for Option<Description> {
+    type Input = Vec<LocalizedValue>;
+    type Error = DescriptionLoadError;
+
+    const XPATH_EXPR: &'static str =
+        "/component/description/p | /component/description/*[self::ul or self::ol]/li";
+
+    fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        if input.iter().any(|paragraph| paragraph.value().is_empty()) {
+            return Err(DescriptionLoadError);
+        }
+
+        let description = Localized::from_paragraphs(&input);
+        Ok(Some(Description(description)))
+    }
+}
+
+#[derive(Clone, Debug, Fail)]
+#[fail(display = "Empty paragraph in `description`")]
+pub struct DescriptionLoadError;
+
+impl Diagnose for DescriptionLoadError {
+    fn tag(&self) -> &'static str {
+        "description-para-empty"
+    }
+}
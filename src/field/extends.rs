@@ -0,0 +1,42 @@
+//! The components an addon `<extends>`.
+
+use std::ops::Deref;
+
+use super::id::{Id, ParseError};
+use super::{Field, ParseOptions};
+
+/// The IDs of the parent components, in document order.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Extends(Vec<Id>);
+
+impl Deref for Extends {
+    type Target = [Id];
+
+    fn deref(&self) -> &[Id] {
+        let Extends(ref ids) = *self;
+        ids.as_slice()
+    }
+}
+
+impl Field for Option<Extends> {
+    type Input = Vec<String>;
+    type Error = ParseError;
+
+    const XPATH_EXPR: &'static str = "/component/extends/text()";
+
+    fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        Self::construct_with(input, &ParseOptions::default())
+    }
+
+    fn construct_with(input: Self::Input, options: &ParseOptions) -> Result<Self, Self::Error> {
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        input
+            .iter()
+            .map(|id| Id::from_str_with_policy(id.trim(), options.tld_policy()))
+            .collect::<Result<_, _>>()
+            .map(|ids| Some(Extends(ids)))
+    }
+}
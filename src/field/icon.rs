@@ -1,10 +1,13 @@
 use std::iter::Iterator;
 use std::path::PathBuf;
 
-use failure::Error;
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde::ser::Serializer;
 use url::Url;
+use xpath_reader::{Error as XpathError, FromXml, Reader};
 
 use super::Field;
+use validate::Diagnose;
 
 #[derive(Debug)]
 pub struct Icons<'a> {
@@ -14,10 +17,7 @@ pub struct Icons<'a> {
 
 impl<'a> Icons<'a> {
     pub(crate) fn new(icons: &'a [Icon]) -> Self {
-        Icons {
-            icons,
-            cur: 0,
-        }
+        Icons { icons, cur: 0 }
     }
 }
 
@@ -30,7 +30,7 @@ impl<'a> Iterator for Icons<'a> {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum Icon {
     Stock {
         id: String,
@@ -44,21 +44,99 @@ pub enum Icon {
         height: Option<u32>,
     },
     Remote {
+        #[serde(deserialize_with = "deserialize_url", serialize_with = "serialize_url")]
         url: Url,
         width: Option<u32>,
         height: Option<u32>,
     },
 }
 
+fn deserialize_url<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Url, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Url::parse(&s).map_err(DeError::custom)
+}
+
+fn serialize_url<S: Serializer>(url: &Url, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(url.as_str())
+}
+
+#[derive(Debug)]
+pub struct IconInput {
+    kind: Option<String>,
+    value: String,
+    width: Option<String>,
+    height: Option<String>,
+}
+
+impl FromXml for IconInput {
+    fn from_xml<'d>(reader: &'d Reader<'d>) -> Result<Self, XpathError> {
+        Ok(IconInput {
+            kind: reader.read("@type")?,
+            value: reader.read(".")?,
+            width: reader.read("@width")?,
+            height: reader.read("@height")?,
+        })
+    }
+}
+
+impl IconInput {
+    fn construct(self) -> Result<Icon, ParseError> {
+        let value = self.value.trim();
+        if value.is_empty() {
+            return Err(ParseError::MissingValue);
+        }
+        let width = parse_dimension(self.width)?;
+        let height = parse_dimension(self.height)?;
+
+        match self.kind.as_deref() {
+            Some("stock") => Ok(Icon::Stock {
+                id: value.to_string(),
+            }),
+            Some("cached") => Ok(Icon::Cached {
+                name: value.to_string(),
+            }),
+            Some("local") => Ok(Icon::Local {
+                path: PathBuf::from(value),
+                width,
+                height,
+            }),
+            Some("remote") => Ok(Icon::Remote {
+                url: Url::parse(value).map_err(|_| ParseError::InvalidUrl(value.to_string()))?,
+                width,
+                height,
+            }),
+            Some(kind) => Err(ParseError::InvalidType(kind.to_string())),
+            None => Err(ParseError::MissingType),
+        }
+    }
+}
+
+fn parse_dimension(value: Option<String>) -> Result<Option<u32>, ParseError> {
+    match value {
+        Some(value) => match value.trim().parse() {
+            Ok(pixels) => Ok(Some(pixels)),
+            Err(_) => Err(ParseError::InvalidSize(value)),
+        },
+        None => Ok(None),
+    }
+}
+
 impl Field for Option<Vec<Icon>> {
-    type Input = Vec<Option<String>>;
+    type Input = Vec<IconInput>;
     type Error = ParseError;
 
-    const XPATH_EXPR: &'static str = "/component/icon/@type | /component/icon[@type]/text()";
+    const XPATH_EXPR: &'static str = "/component/icon";
 
     fn construct(input: Self::Input) -> Result<Self, Self::Error> {
-        println!("{:?}", input);
-        Ok(None)
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        input
+            .into_iter()
+            .map(IconInput::construct)
+            .collect::<Result<_, _>>()
+            .map(Some)
     }
 }
 
@@ -66,6 +144,27 @@ impl Field for Option<Vec<Icon>> {
 pub enum ParseError {
     #[fail(display = "Unexpected field `{}` with value `{}`", _0, _1)]
     UnexpctedAttribute { field: String, value: String },
+    #[fail(display = "Missing icon `type` attribute")]
+    MissingType,
     #[fail(display = "Invalid icon type `{}`", _0)]
     InvalidType(String),
+    #[fail(display = "Empty icon")]
+    MissingValue,
+    #[fail(display = "Invalid icon size `{}`", _0)]
+    InvalidSize(String),
+    #[fail(display = "Invalid icon URL `{}`", _0)]
+    InvalidUrl(String),
+}
+
+impl Diagnose for ParseError {
+    fn tag(&self) -> &'static str {
+        match *self {
+            ParseError::UnexpctedAttribute { .. } => "icon-attribute-unexpected",
+            ParseError::MissingType => "icon-type-missing",
+            ParseError::InvalidType(_) => "icon-type-invalid",
+            ParseError::MissingValue => "icon-value-missing",
+            ParseError::InvalidSize(_) => "icon-size-invalid",
+            ParseError::InvalidUrl(_) => "icon-url-invalid",
+        }
+    }
 }
@@ -1,15 +1,24 @@
 //! Represents an AppStream package ID.
 
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::str::FromStr;
 
-use super::Field;
+use serde::de::{Deserialize, Deserializer, Error as DeError, Visitor};
+use serde::ser::{Serialize, Serializer};
 
+use super::{Field, ParseOptions};
+use validate::Diagnose;
+
+/// Legacy suffix from when component IDs were named after their `.desktop` file.
+const DESKTOP_SUFFIX: &str = ".desktop";
+
+/// A reverse-DNS component ID, e.g. `io.github.user.App`.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Id {
     tld: TopLevelDomain,
-    vendor: String,
-    product: String,
+    segments: Vec<String>,
+    desktop_suffix: bool,
 }
 
 impl Id {
@@ -19,8 +28,8 @@ impl Id {
     {
         Id {
             tld,
-            vendor: vendor.into(),
-            product: product.into(),
+            segments: vec![vendor.into(), product.into()],
+            desktop_suffix: false,
         }
     }
 
@@ -28,28 +37,98 @@ impl Id {
     where
         S: AsRef<str>,
     {
-        let fields = [tld.as_ref(), vendor.as_ref(), product.as_ref()];
+        Id::parse_with_policy(tld, vendor, product, &TldPolicy::default())
+    }
+
+    /// Like `parse`, but checks the top-level domain against the given `policy`.
+    pub fn parse_with_policy<S>(
+        tld: S,
+        vendor: S,
+        product: S,
+        policy: &TldPolicy,
+    ) -> Result<Id, ParseError>
+    where
+        S: AsRef<str>,
+    {
+        let id = format!("{}.{}.{}", tld.as_ref(), vendor.as_ref(), product.as_ref());
+        Id::from_str_with_policy(&id, policy)
+    }
+
+    /// Like `from_str`, but checks the top-level domain against the given `policy`.
+    pub fn from_str_with_policy(s: &str, policy: &TldPolicy) -> Result<Id, ParseError> {
+        let (body, desktop_suffix) = match s.rfind(DESKTOP_SUFFIX) {
+            Some(i) if i + DESKTOP_SUFFIX.len() == s.len() && s[..i].matches('.').count() >= 2 => {
+                (&s[..i], true)
+            }
+            _ => (s, false),
+        };
+
+        let fields: Vec<&str> = body.split('.').collect();
+        if fields.len() < 3 {
+            return Err(ParseError::WrongNumFields);
+        }
 
         for (i, field) in fields.iter().enumerate() {
+            if field.is_empty() {
+                return Err(ParseError::EmptySegment { field: i });
+            }
+
             for ch in field.chars() {
-                if !ch.is_alphanumeric() && ch != '-' && ch != '_' && ch != '.' {
+                if !ch.is_ascii_alphanumeric() && ch != '-' && ch != '_' {
                     return Err(ParseError::InvalidCharacter { field: i, ch });
                 }
             }
+
+            if field.starts_with(|ch: char| ch.is_ascii_digit()) {
+                return Err(ParseError::NumberPrefix(field.to_string()));
+            }
         }
 
-        let tld = TopLevelDomain::from_str(&fields[0]).map_err(ParseError::UnrecognizedTld)?;
-        let vendor = fields[1];
-        let product = fields[2];
+        let tld = TopLevelDomain::parse(fields[0], policy).map_err(ParseError::UnrecognizedTld)?;
+        let segments = fields[1..].iter().map(|s| s.to_string()).collect();
+
+        Ok(Id {
+            tld,
+            segments,
+            desktop_suffix,
+        })
+    }
+
+    pub fn tld(&self) -> &TopLevelDomain {
+        &self.tld
+    }
+
+    /// Returns every segment of the ID including the top-level domain, without any legacy
+    /// `.desktop` suffix.
+    pub fn segments(&self) -> Vec<&str> {
+        let rest = self.segments.iter().map(|s| s.as_str());
+        Some(self.tld.as_str()).into_iter().chain(rest).collect()
+    }
+
+    /// Returns the reverse-DNS domain part, e.g. `io.github.user` for `io.github.user.App`.
+    pub fn domain(&self) -> String {
+        let segments = self.segments();
+        segments[..segments.len() - 1].join(".")
+    }
+
+    /// Returns the application name, i.e. the last segment, e.g. `App` for `io.github.user.App`.
+    pub fn app_name(&self) -> &str {
+        self.segments.last().map(|s| s.as_str()).unwrap_or("")
+    }
 
-        Ok(Id::new(tld, vendor, product))
+    /// Returns whether the ID ends with the legacy `.desktop` suffix.
+    pub fn has_desktop_suffix(&self) -> bool {
+        self.desktop_suffix
     }
 }
 
 impl Display for Id {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
-        let out = format!("{}.{}.{}", self.tld, self.vendor, self.product);
-        fmt.write_str(&out)
+        fmt.write_str(&self.segments().join("."))?;
+        if self.desktop_suffix {
+            fmt.write_str(DESKTOP_SUFFIX)?;
+        }
+        Ok(())
     }
 }
 
@@ -57,13 +136,41 @@ impl FromStr for Id {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let fields: Vec<&str> = s.splitn(3, '.').collect();
+        Id::from_str_with_policy(s, &TldPolicy::default())
+    }
+}
 
-        if fields.len() != 3 {
-            return Err(ParseError::WrongNumFields);
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct IdVisitor;
+
+        impl<'de> Visitor<'de> for IdVisitor {
+            type Value = Id;
+
+            fn expecting(&self, fmt: &mut Formatter) -> FmtResult {
+                fmt.write_str("string containing a reverse-DNS component ID")
+            }
+
+            // Serialized IDs have already been checked against a `TldPolicy` when parsed.
+            fn visit_str<E: DeError>(self, v: &str) -> Result<Self::Value, E> {
+                Id::from_str_with_policy(v, &TldPolicy::Permissive).map_err(DeError::custom)
+            }
         }
 
-        Id::parse(fields[0], fields[1], fields[2])
+        deserializer.deserialize_str(IdVisitor)
+    }
+}
+
+impl Serialize for Id {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let out = self.to_string();
+        serializer.serialize_str(&out)
     }
 }
 
@@ -76,22 +183,103 @@ impl Field for Id {
     fn construct(input: Self::Input) -> Result<Self, Self::Error> {
         Id::from_str(&input)
     }
+
+    fn construct_with(input: Self::Input, options: &ParseOptions) -> Result<Self, Self::Error> {
+        Id::from_str_with_policy(&input, options.tld_policy())
+    }
 }
 
 #[derive(Clone, Debug, Fail)]
 pub enum ParseError {
-    #[fail(display = "Expected three fields separated by `.` characters")]
+    #[fail(display = "Expected at least three fields separated by `.` characters")]
     WrongNumFields,
+    #[fail(display = "Field {} is empty", field)]
+    EmptySegment { field: usize },
     #[fail(display = "Invalid character `{}` in field {}", ch, field)]
     InvalidCharacter { ch: char, field: usize },
+    #[fail(display = "Field `{}` starts with a digit and must be prefixed with `_`", _0)]
+    NumberPrefix(String),
     #[fail(display = "{}", _0)]
     UnrecognizedTld(#[cause] UnrecognizedTldError),
 }
 
-/// An IANA registered top-level domain.
+impl Diagnose for ParseError {
+    fn tag(&self) -> &'static str {
+        match *self {
+            ParseError::WrongNumFields => "cid-missing-parts",
+            ParseError::EmptySegment { .. } => "cid-segment-empty",
+            ParseError::InvalidCharacter { .. } => "cid-invalid-character",
+            ParseError::NumberPrefix(_) => "cid-has-number-prefix",
+            ParseError::UnrecognizedTld(_) => "cid-domain-unknown",
+        }
+    }
+}
+
+/// Special-use names from RFC 2606 and RFC 6761, accepted regardless of policy.
+const RESERVED_TLDS: &[&str] = &["example", "invalid", "local", "localhost", "test"];
+
+lazy_static! {
+    static ref IANA_TLDS: HashSet<&'static str> = include_str!("tlds.txt")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+}
+
+/// Decides which top-level domains are accepted in component IDs.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum TldPolicy {
+    /// Accepts the IANA domains embedded in this crate, independent of the system.
+    #[default]
+    Iana,
+    /// Accepts any top-level domain.
+    Permissive,
+    /// Accepts only the domains supplied by the caller.
+    Custom(HashSet<String>),
+}
+
+impl TldPolicy {
+    pub fn custom<I, S>(tlds: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let tlds = tlds.into_iter().map(|s| s.as_ref().to_lowercase()).collect();
+        TldPolicy::Custom(tlds)
+    }
+
+    pub fn accepts(&self, tld: &str) -> bool {
+        let tld = tld.to_lowercase();
+        if RESERVED_TLDS.contains(&tld.as_str()) {
+            return true;
+        }
+
+        match *self {
+            TldPolicy::Iana => IANA_TLDS.contains(tld.as_str()),
+            TldPolicy::Permissive => !tld.is_empty(),
+            TldPolicy::Custom(ref tlds) => tlds.contains(&tld),
+        }
+    }
+}
+
+/// A top-level domain accepted by a `TldPolicy`.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct TopLevelDomain(String);
 
+impl TopLevelDomain {
+    pub fn parse(s: &str, policy: &TldPolicy) -> Result<Self, UnrecognizedTldError> {
+        if policy.accepts(s) {
+            Ok(TopLevelDomain(s.to_string()))
+        } else {
+            Err(UnrecognizedTldError(s.to_string()))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        let TopLevelDomain(ref s) = *self;
+        s.as_str()
+    }
+}
+
 impl Display for TopLevelDomain {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
         let TopLevelDomain(ref s) = *self;
@@ -103,17 +291,47 @@ impl FromStr for TopLevelDomain {
     type Err = UnrecognizedTldError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        use psl::{List, Psl};
-
-        let domain = s.to_string();
-        if List::new().domain(&format!("example.{}", domain)).is_some() {
-            Ok(TopLevelDomain(domain))
-        } else {
-            Err(UnrecognizedTldError(domain))
-        }
+        TopLevelDomain::parse(s, &TldPolicy::default())
     }
 }
 
 #[derive(Clone, Debug, Fail)]
-#[fail(display = "Invalid IANA top-level domain: {}", _0)]
+#[fail(display = "Unrecognized top-level domain: {}", _0)]
 pub struct UnrecognizedTldError(String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structured_segments() {
+        let id = Id::from_str("io.github.user.App").unwrap();
+        assert_eq!(id.segments(), vec!["io", "github", "user", "App"]);
+        assert_eq!(id.domain(), "io.github.user");
+        assert_eq!(id.app_name(), "App");
+        assert_eq!(id.to_string(), "io.github.user.App");
+    }
+
+    #[test]
+    fn desktop_suffix() {
+        let id = Id::from_str("org.gnome.gedit.desktop").unwrap();
+        assert!(id.has_desktop_suffix());
+        assert_eq!(id.app_name(), "gedit");
+        assert_eq!(id.to_string(), "org.gnome.gedit.desktop");
+
+        let id = Id::from_str("org.example.desktop").unwrap();
+        assert!(!id.has_desktop_suffix());
+        assert_eq!(id.app_name(), "desktop");
+    }
+
+    #[test]
+    fn invalid_segments() {
+        match Id::from_str("org.example.0ad") {
+            Err(ParseError::NumberPrefix(ref s)) => assert_eq!(s, "0ad"),
+            other => panic!("expected number prefix error, got {:?}", other),
+        }
+        assert!(Id::from_str("org.example._0ad").is_ok());
+        assert!(Id::from_str("org..foo").is_err());
+        assert!(Id::from_str("org.foo").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+//! The translatable search `<keywords>` of a component.
+
+use std::collections::BTreeMap;
+
+use super::localized::{lookup, LocalizedValue};
+use super::Field;
+use validate::Diagnose;
+
+/// Search keywords, with an untranslated list and a list per translated locale.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Keywords {
+    default: Vec<String>,
+    translations: BTreeMap<String, Vec<String>>,
+}
+
+impl Keywords {
+    /// Returns the untranslated keywords.
+    pub fn default_values(&self) -> &[String] {
+        self.default.as_slice()
+    }
+
+    /// Returns the keywords for `locale`, falling back like `Localized::get`.
+    pub fn get(&self, locale: &str) -> &[String] {
+        lookup(&self.translations, locale)
+            .map(|keywords| keywords.as_slice())
+            .unwrap_or_else(|| self.default_values())
+    }
+
+    /// Iterates over the translated keyword lists by locale.
+    pub fn translations(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.translations
+            .iter()
+            .map(|(locale, keywords)| (locale.as_str(), keywords.as_slice()))
+    }
+}
+
+impl Field for Option<Keywords> {
+    type Input = Vec<LocalizedValue>;
+    type Error = KeywordsLoadError;
+
+    const XPATH_EXPR: &'static str = "/component/keywords/keyword";
+
+    fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let mut keywords = Keywords::default();
+        for keyword in input {
+            if keyword.value().is_empty() {
+                return Err(KeywordsLoadError);
+            }
+
+            let list = match keyword.locale() {
+                Some(locale) => keywords.translations.entry(locale.to_string()).or_default(),
+                None => &mut keywords.default,
+            };
+            list.push(keyword.value().to_string());
+        }
+
+        Ok(Some(keywords))
+    }
+}
+
+#[derive(Clone, Debug, Fail)]
+#[fail(display = "Empty `keyword`")]
+pub struct KeywordsLoadError;
+
+impl Diagnose for KeywordsLoadError {
+    fn tag(&self) -> &'static str {
+        "keyword-empty"
+    }
+}
@@ -1,6 +1,9 @@
 //! SPDX license expression parsing.
 
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::iter::Peekable;
+use std::ops::Deref;
 use std::str::FromStr;
 
 use license_exprs::{validate_license_expr, ParseError as LicenseParseError};
@@ -8,15 +11,240 @@ use serde::de::{Deserialize, Deserializer, Error as DeError, Visitor};
 use serde::ser::{Serialize, Serializer};
 
 use field::Field;
+use validate::Diagnose;
+
+/// Licenses the specification permits for `<metadata_license>`, alone or combined.
+const METADATA_LICENSES: &[&str] = &[
+    "FSFAP",
+    "MIT",
+    "0BSD",
+    "CC0-1.0",
+    "CC-BY-3.0",
+    "CC-BY-4.0",
+    "CC-BY-SA-3.0",
+    "CC-BY-SA-4.0",
+    "GFDL-1.1",
+    "GFDL-1.2",
+    "GFDL-1.3",
+    "BSL-1.0",
+    "FTL",
+];
+
+/// Prefix of custom license references which are not part of the SPDX license list.
+const LICENSE_REF_PREFIX: &str = "LicenseRef-";
+
+/// Custom reference for a free license not on the SPDX list, given as `LicenseRef-free=URL`.
+const LICENSE_REF_FREE: &str = "LicenseRef-free";
+
+lazy_static! {
+    static ref FREE_LICENSES: HashSet<&'static str> = include_str!("free_licenses.txt")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+}
+
+/// A parsed SPDX license expression.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum LicenseExpr {
+    /// An SPDX license identifier, e.g. `GPL-2.0` or `GPL-2.0+` with `or_later` set.
+    License { id: String, or_later: bool },
+    /// A custom `LicenseRef-...` reference with an optional `=URL` pointing to the license text.
+    Ref { name: String, url: Option<String> },
+    /// A license with an additional exception, e.g. `GPL-2.0 WITH Classpath-exception-2.0`.
+    With(Box<LicenseExpr>, String),
+    And(Box<LicenseExpr>, Box<LicenseExpr>),
+    Or(Box<LicenseExpr>, Box<LicenseExpr>),
+}
+
+impl LicenseExpr {
+    /// Returns whether the expression grants free software rights.
+    ///
+    /// A license is free if it is FSF libre or OSI approved, or is a `LicenseRef-free`
+    /// reference. For `OR` any alternative suffices, while `AND` requires both sides.
+    pub fn is_free(&self) -> bool {
+        match *self {
+            LicenseExpr::License { ref id, .. } => FREE_LICENSES.contains(id.as_str()),
+            LicenseExpr::Ref { ref name, .. } => name == LICENSE_REF_FREE,
+            LicenseExpr::With(ref license, _) => license.is_free(),
+            LicenseExpr::And(ref lhs, ref rhs) => lhs.is_free() && rhs.is_free(),
+            LicenseExpr::Or(ref lhs, ref rhs) => lhs.is_free() || rhs.is_free(),
+        }
+    }
+
+    /// Returns whether the expression can be satisfied using only the `allowed` licenses.
+    ///
+    /// Entries are SPDX identifiers or `LicenseRef-...` names. An exception never restricts
+    /// a license further, so `X WITH Y` is satisfied whenever `X` is allowed.
+    pub fn satisfies(&self, allowed: &[&str]) -> bool {
+        match *self {
+            LicenseExpr::License { ref id, or_later } => {
+                let plus = format!("{}+", id);
+                allowed.contains(&id.as_str()) || (or_later && allowed.contains(&plus.as_str()))
+            }
+            LicenseExpr::Ref { ref name, .. } => allowed.contains(&name.as_str()),
+            LicenseExpr::With(ref license, _) => license.satisfies(allowed),
+            LicenseExpr::And(ref lhs, ref rhs) => lhs.satisfies(allowed) && rhs.satisfies(allowed),
+            LicenseExpr::Or(ref lhs, ref rhs) => lhs.satisfies(allowed) || rhs.satisfies(allowed),
+        }
+    }
+
+    /// Returns whether the expression permits use as `<metadata_license>`.
+    ///
+    /// For `OR` at least one alternative must be a metadata license, while `AND` requires both
+    /// sides. Exceptions only grant additional permissions, so `X WITH Y` is accepted if `X` is.
+    pub fn is_metadata_license(&self) -> bool {
+        match *self {
+            LicenseExpr::License { ref id, .. } => {
+                let id = id.trim_end_matches("-or-later").trim_end_matches("-only");
+                METADATA_LICENSES.contains(&id)
+            }
+            LicenseExpr::Ref { .. } => false,
+            LicenseExpr::With(ref license, _) => license.is_metadata_license(),
+            LicenseExpr::And(ref lhs, ref rhs) => {
+                lhs.is_metadata_license() && rhs.is_metadata_license()
+            }
+            LicenseExpr::Or(ref lhs, ref rhs) => {
+                lhs.is_metadata_license() || rhs.is_metadata_license()
+            }
+        }
+    }
+
+    /// Returns whether this is the `LicenseRef-proprietary` reference.
+    pub fn is_proprietary(&self) -> bool {
+        match *self {
+            LicenseExpr::Ref { ref name, .. } => name == "LicenseRef-proprietary",
+            _ => false,
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, ParseError> {
+        let spaced = text.replace('(', " ( ").replace(')', " ) ");
+        let mut tokens = spaced.split_whitespace().peekable();
+        let expr = LicenseExpr::parse_or(&mut tokens)?;
+
+        match tokens.next() {
+            None => Ok(expr),
+            Some(_) => Err(ParseError::InvalidStructure),
+        }
+    }
+
+    fn parse_or<'a, I>(tokens: &mut Peekable<I>) -> Result<Self, ParseError>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut expr = LicenseExpr::parse_and(tokens)?;
+        while tokens.peek() == Some(&"OR") {
+            tokens.next();
+            let rhs = LicenseExpr::parse_and(tokens)?;
+            expr = LicenseExpr::Or(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and<'a, I>(tokens: &mut Peekable<I>) -> Result<Self, ParseError>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut expr = LicenseExpr::parse_with(tokens)?;
+        while tokens.peek() == Some(&"AND") {
+            tokens.next();
+            let rhs = LicenseExpr::parse_with(tokens)?;
+            expr = LicenseExpr::And(Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    fn parse_with<'a, I>(tokens: &mut Peekable<I>) -> Result<Self, ParseError>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let expr = LicenseExpr::parse_primary(tokens)?;
+        if tokens.peek() != Some(&"WITH") {
+            return Ok(expr);
+        }
+
+        tokens.next();
+        let exception = tokens.next().ok_or(ParseError::InvalidStructure)?;
+        match expr {
+            LicenseExpr::License { .. } => {}
+            _ => return Err(ParseError::InvalidStructure),
+        }
+
+        validate_license_expr(&format!("MIT WITH {}", exception)).map_err(ParseError::from)?;
+        Ok(LicenseExpr::With(Box::new(expr), exception.to_string()))
+    }
+
+    fn parse_primary<'a, I>(tokens: &mut Peekable<I>) -> Result<Self, ParseError>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        match tokens.next() {
+            Some("(") => {
+                let expr = LicenseExpr::parse_or(tokens)?;
+                match tokens.next() {
+                    Some(")") => Ok(expr),
+                    _ => Err(ParseError::InvalidStructure),
+                }
+            }
+            Some(")") | Some("AND") | Some("OR") | Some("WITH") | None => {
+                Err(ParseError::InvalidStructure)
+            }
+            Some(token) if token.starts_with(LICENSE_REF_PREFIX) => {
+                let mut parts = token.splitn(2, '=');
+                let name = parts.next().unwrap_or(token);
+                if name.len() == LICENSE_REF_PREFIX.len() {
+                    return Err(ParseError::UnknownLicenseId(token.into()));
+                }
+
+                Ok(LicenseExpr::Ref {
+                    name: name.to_string(),
+                    url: parts.next().map(|url| url.to_string()),
+                })
+            }
+            Some(token) => {
+                validate_license_expr(token).map_err(ParseError::from)?;
+                Ok(LicenseExpr::License {
+                    id: token.trim_end_matches('+').to_string(),
+                    or_later: token.ends_with('+'),
+                })
+            }
+        }
+    }
+}
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct License(String);
+pub struct License {
+    text: String,
+    expr: LicenseExpr,
+}
 
 impl License {
     pub fn new<E: Into<String>>(expr: E) -> Result<Self, ParseError> {
-        let inner = expr.into();
-        validate_license_expr(&inner).map_err(ParseError::from)?;
-        Ok(License(inner))
+        let text = expr.into();
+        let expr = LicenseExpr::parse(&text)?;
+        Ok(License { text, expr })
+    }
+
+    /// Returns the parsed expression tree.
+    pub fn expr(&self) -> &LicenseExpr {
+        &self.expr
+    }
+
+    /// Returns whether the whole expression is free software. See `LicenseExpr::is_free`.
+    pub fn is_free(&self) -> bool {
+        self.expr.is_free()
+    }
+
+    /// Returns whether the expression satisfies the given allow-list. See
+    /// `LicenseExpr::satisfies`.
+    pub fn satisfies(&self, allowed: &[&str]) -> bool {
+        self.expr.satisfies(allowed)
+    }
+
+    /// Returns whether the expression permits use as `<metadata_license>`. See
+    /// `LicenseExpr::is_metadata_license`.
+    pub fn is_metadata_license(&self) -> bool {
+        self.expr.is_metadata_license()
     }
 }
 
@@ -45,8 +273,7 @@ impl<'de> Deserialize<'de> for License {
 
 impl Display for License {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
-        let License(ref text) = *self;
-        text.fmt(fmt)
+        self.text.fmt(fmt)
     }
 }
 
@@ -82,6 +309,33 @@ impl Field for Option<License> {
     }
 }
 
+/// The license of the metadata itself, read from `<metadata_license>`.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct MetadataLicense(License);
+
+impl Deref for MetadataLicense {
+    type Target = License;
+
+    fn deref(&self) -> &License {
+        let MetadataLicense(ref license) = *self;
+        license
+    }
+}
+
+impl Field for Option<MetadataLicense> {
+    type Input = Option<String>;
+    type Error = ParseError;
+
+    const XPATH_EXPR: &'static str = "/component/metadata_license/text()";
+
+    fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        match input {
+            Some(s) => License::from_str(&s).map(|l| Some(MetadataLicense(l))),
+            None => Ok(None),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Fail)]
 pub enum ParseError {
     #[fail(display = "unknown license or other term: {}", _0)]
@@ -90,6 +344,22 @@ pub enum ParseError {
     InvalidStructure,
 }
 
+impl Diagnose for ParseError {
+    fn tag(&self) -> &'static str {
+        match *self {
+            ParseError::UnknownLicenseId(_) => "spdx-license-unknown",
+            ParseError::InvalidStructure => "spdx-expression-invalid",
+        }
+    }
+
+    fn subject(&self) -> Option<&str> {
+        match *self {
+            ParseError::UnknownLicenseId(ref id) => Some(id),
+            ParseError::InvalidStructure => None,
+        }
+    }
+}
+
 impl<'a> From<LicenseParseError<'a>> for ParseError {
     fn from(error: LicenseParseError<'a>) -> Self {
         match error {
@@ -98,3 +368,78 @@ impl<'a> From<LicenseParseError<'a>> for ParseError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tree() {
+        let license = License::new("(MIT OR GPL-2.0+) AND Apache-2.0 WITH LLVM-exception").unwrap();
+        let expected = LicenseExpr::And(
+            Box::new(LicenseExpr::Or(
+                Box::new(LicenseExpr::License {
+                    id: "MIT".into(),
+                    or_later: false,
+                }),
+                Box::new(LicenseExpr::License {
+                    id: "GPL-2.0".into(),
+                    or_later: true,
+                }),
+            )),
+            Box::new(LicenseExpr::With(
+                Box::new(LicenseExpr::License {
+                    id: "Apache-2.0".into(),
+                    or_later: false,
+                }),
+                "LLVM-exception".into(),
+            )),
+        );
+        assert_eq!(license.expr(), &expected);
+        assert_eq!(license.to_string(), "(MIT OR GPL-2.0+) AND Apache-2.0 WITH LLVM-exception");
+    }
+
+    #[test]
+    fn parse_refs() {
+        let license = License::new("LicenseRef-free=https://example.org/license").unwrap();
+        match *license.expr() {
+            LicenseExpr::Ref { ref name, ref url } => {
+                assert_eq!(name, "LicenseRef-free");
+                assert_eq!(url.as_deref(), Some("https://example.org/license"));
+            }
+            ref other => panic!("expected license reference, got {:?}", other),
+        }
+        assert!(license.is_free());
+
+        let license = License::new("LicenseRef-proprietary").unwrap();
+        assert!(license.expr().is_proprietary());
+        assert!(!license.is_free());
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        assert!(License::new("MIT OR").is_err());
+        assert!(License::new("(MIT").is_err());
+        assert!(License::new("MIT AND (Apache-2.0 OR)").is_err());
+        assert!(License::new("Bogus-1.0").is_err());
+    }
+
+    #[test]
+    fn classification() {
+        assert!(License::new("GPL-3.0 OR LicenseRef-proprietary").unwrap().is_free());
+        assert!(!License::new("GPL-3.0 AND LicenseRef-proprietary").unwrap().is_free());
+        assert!(!License::new("CC-BY-NC-4.0").unwrap().is_free());
+
+        let allowed = &["MIT", "GPL-2.0+"];
+        assert!(License::new("MIT AND GPL-2.0+").unwrap().satisfies(allowed));
+        assert!(License::new("Apache-2.0 OR MIT").unwrap().satisfies(allowed));
+        assert!(!License::new("MIT AND Apache-2.0").unwrap().satisfies(allowed));
+
+        let metadata = |expr: &str| License::new(expr).unwrap().is_metadata_license();
+        assert!(metadata("MIT OR Apache-2.0"));
+        assert!(metadata("CC0-1.0 AND FSFAP"));
+        assert!(metadata("MIT WITH Font-exception-2.0"));
+        assert!(!metadata("MIT AND Apache-2.0"));
+        assert!(!metadata("GPL-3.0 WITH Classpath-exception-2.0"));
+    }
+}
@@ -0,0 +1,146 @@
+//! Translatable text with an optional `xml:lang` locale per value.
+
+use std::collections::BTreeMap;
+
+use xpath_reader::{Error as XpathError, FromXml, Reader};
+
+/// A text value with an untranslated (C locale) variant and any number of translations.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Localized {
+    default: Option<String>,
+    translations: BTreeMap<String, String>,
+}
+
+impl Localized {
+    pub fn new<S: Into<String>>(default: S) -> Self {
+        Localized {
+            default: Some(default.into()),
+            translations: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the untranslated value, if one was declared.
+    pub fn default_value(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    /// Returns the value for `locale`, falling back to its language (`de` for `de_DE`) and then
+    /// to the untranslated value.
+    pub fn get(&self, locale: &str) -> Option<&str> {
+        lookup(&self.translations, locale)
+            .map(|s| s.as_str())
+            .or_else(|| self.default_value())
+    }
+
+    /// Iterates over all `(locale, value)` translation pairs.
+    pub fn translations(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.translations
+            .iter()
+            .map(|(locale, value)| (locale.as_str(), value.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.default.is_none() && self.translations.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, locale: Option<String>, value: String) {
+        match locale {
+            Some(locale) => {
+                self.translations.insert(locale, value);
+            }
+            None => self.default = Some(value),
+        }
+    }
+
+    /// Joins paragraphs with blank lines, keeping each locale's paragraphs in document order.
+    pub(crate) fn from_paragraphs(paragraphs: &[LocalizedValue]) -> Self {
+        let mut grouped: BTreeMap<Option<&str>, Vec<&str>> = BTreeMap::new();
+        for paragraph in paragraphs {
+            grouped
+                .entry(paragraph.locale())
+                .or_default()
+                .push(paragraph.value());
+        }
+
+        let mut localized = Localized::default();
+        for (locale, text) in grouped {
+            localized.insert(locale.map(String::from), text.join("\n\n"));
+        }
+        localized
+    }
+
+    /// Copies the untranslated value and translations of `other` which are missing here.
+    pub(crate) fn fill_from(&mut self, other: &Localized) {
+        if self.default.is_none() {
+            self.default = other.default.clone();
+        }
+
+        for (locale, value) in &other.translations {
+            self.translations
+                .entry(locale.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+}
+
+/// Looks up `locale` in `map`, dropping the `@modifier` and then the `_TERRITORY` if needed.
+pub(crate) fn lookup<'a, T>(map: &'a BTreeMap<String, T>, locale: &str) -> Option<&'a T> {
+    let without_modifier = locale.split('@').next().unwrap_or(locale);
+    let language = without_modifier.split('_').next().unwrap_or(without_modifier);
+
+    map.get(locale)
+        .or_else(|| map.get(without_modifier))
+        .or_else(|| map.get(language))
+}
+
+/// A single translatable value, e.g. one `<keyword>` or description paragraph.
+#[derive(Debug)]
+pub struct LocalizedValue {
+    locale: Option<String>,
+    value: String,
+}
+
+impl LocalizedValue {
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl FromXml for LocalizedValue {
+    fn from_xml<'d>(reader: &'d Reader<'d>) -> Result<Self, XpathError> {
+        // The locale may also be set on an enclosing element, e.g. `<keywords xml:lang="de">`.
+        let locale = "ancestor-or-self::*[not(self::component)]/@*[local-name() = 'lang']";
+        let value: String = reader.read(".")?;
+
+        Ok(LocalizedValue {
+            locale: reader.read(locale)?,
+            value: value.trim().to_string(),
+        })
+    }
+}
+
+impl FromXml for Localized {
+    fn from_xml<'d>(reader: &'d Reader<'d>) -> Result<Self, XpathError> {
+        let mut localized = Localized::default();
+
+        for node in reader.anchor_nodeset().document_order() {
+            let reader = Reader::from_node(node, Some(reader.context()));
+            // Markup such as `<description>` is split into paragraphs, like `Description`.
+            let paragraphs: Vec<LocalizedValue> = reader.read("p | ul/li | ol/li")?;
+            if !paragraphs.is_empty() {
+                localized.fill_from(&Localized::from_paragraphs(&paragraphs));
+                continue;
+            }
+
+            let locale: Option<String> = reader.read("@*[local-name() = 'lang']")?;
+            let value = node.string_value().trim().to_string();
+            localized.insert(locale, value);
+        }
+
+        Ok(localized)
+    }
+}
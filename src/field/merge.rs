@@ -0,0 +1,70 @@
+//! The `merge` attribute of catalog components which amend components from other sources.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use super::Field;
+use validate::Diagnose;
+
+/// How a `<component merge="...">` entry changes the component with the same id.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum MergeKind {
+    /// Adds the values of the merge component to the existing ones.
+    Append,
+    /// Overwrites the fields which are set in the merge component.
+    Replace,
+    /// Removes the component from the pool.
+    RemoveComponent,
+}
+
+impl FromStr for MergeKind {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "append" => Ok(MergeKind::Append),
+            "replace" => Ok(MergeKind::Replace),
+            "remove-component" => Ok(MergeKind::RemoveComponent),
+            _ => Err(ParseError(s.to_string())),
+        }
+    }
+}
+
+impl Display for MergeKind {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            MergeKind::Append => fmt.write_str("append"),
+            MergeKind::Replace => fmt.write_str("replace"),
+            MergeKind::RemoveComponent => fmt.write_str("remove-component"),
+        }
+    }
+}
+
+impl Field for Option<MergeKind> {
+    type Input = Option<String>;
+    type Error = ParseError;
+
+    const XPATH_EXPR: &'static str = "/component/@merge";
+
+    fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        match input {
+            Some(s) => MergeKind::from_str(s.trim()).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Fail)]
+#[fail(display = "unknown merge kind: {}", _0)]
+pub struct ParseError(String);
+
+impl Diagnose for ParseError {
+    fn tag(&self) -> &'static str {
+        "merge-kind-invalid"
+    }
+
+    fn subject(&self) -> Option<&str> {
+        let ParseError(ref kind) = *self;
+        Some(kind)
+    }
+}
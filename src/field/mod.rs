@@ -1,26 +1,71 @@
 use std::fmt::Debug;
 
-use failure::Fail;
 use xpath_reader::{Error as XpathError, FromXml, Reader};
 
+use self::id::TldPolicy;
+use validate::Diagnose;
+
+pub mod agreement;
+pub mod bundle;
 pub mod category;
 pub mod copyright;
+pub mod description;
+pub mod extends;
 pub mod icon;
 pub mod id;
+pub mod keywords;
 pub mod license;
+pub mod localized;
+pub mod merge;
 pub mod name;
 pub mod pkg_name;
+pub mod provides;
 pub mod summary;
 
 pub trait Field: Sized + Debug {
     type Input: FromXml;
-    type Error: Fail + Send + Sync + 'static;
+    type Error: Diagnose + Send + Sync + 'static;
 
     const XPATH_EXPR: &'static str;
 
     fn construct(input: Self::Input) -> Result<Self, Self::Error>;
 
+    /// Constructs the field honoring the parser options; most fields ignore them.
+    fn construct_with(input: Self::Input, _options: &ParseOptions) -> Result<Self, Self::Error> {
+        Self::construct(input)
+    }
+
     fn load<'d>(reader: &'d Reader<'d>) -> Result<Self::Input, XpathError> {
         reader.read(Self::XPATH_EXPR)
     }
 }
+
+/// Options which affect how fields are parsed.
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    tld_policy: TldPolicy,
+    partial: bool,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        ParseOptions::default()
+    }
+
+    pub fn tld_policy(&self) -> &TldPolicy {
+        &self.tld_policy
+    }
+
+    pub fn set_tld_policy(&mut self, policy: TldPolicy) {
+        self.tld_policy = policy;
+    }
+
+    /// Whether required fields may be missing, as in catalog components with a `merge` attribute.
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    pub fn set_partial(&mut self, partial: bool) {
+        self.partial = partial;
+    }
+}
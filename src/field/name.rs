@@ -1,34 +1,73 @@
 use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::Deref;
 
-use super::Field;
+use super::localized::Localized;
+use super::{Field, ParseOptions};
+use validate::Diagnose;
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct Name(String);
+/// The translatable `<name>` of a component.
+///
+/// Displays as the untranslated value, which may be missing if only translations were given.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Name(Localized);
+
+impl Name {
+    pub(crate) fn localized_mut(&mut self) -> &mut Localized {
+        let Name(ref mut value) = *self;
+        value
+    }
+}
+
+impl Deref for Name {
+    type Target = Localized;
+
+    fn deref(&self) -> &Localized {
+        let Name(ref name) = *self;
+        name
+    }
+}
 
 impl Display for Name {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
-        let Name(ref name) = *self;
-        name.fmt(fmt)
+        self.default_value().unwrap_or("").fmt(fmt)
     }
 }
 
 impl From<String> for Name {
     fn from(s: String) -> Self {
-        Name(s)
+        Name(Localized::new(s))
     }
 }
 
 impl Field for Name {
-    type Input = String;
+    type Input = Localized;
     type Error = NameLoadError;
 
-    const XPATH_EXPR: &'static str = "/component/name/text()";
+    const XPATH_EXPR: &'static str = "/component/name";
 
     fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        if input.is_empty() {
+            return Err(NameLoadError);
+        }
+
         Ok(Name(input))
     }
+
+    fn construct_with(input: Self::Input, options: &ParseOptions) -> Result<Self, Self::Error> {
+        if options.is_partial() {
+            return Ok(Name(input));
+        }
+
+        Self::construct(input)
+    }
 }
 
 #[derive(Clone, Debug, Fail)]
 #[fail(display = "Failed to load `name`")]
 pub struct NameLoadError;
+
+impl Diagnose for NameLoadError {
+    fn tag(&self) -> &'static str {
+        "name-invalid"
+    }
+}
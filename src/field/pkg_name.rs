@@ -1,8 +1,10 @@
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 use super::Field;
+use validate::Diagnose;
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// A distribution package shipping the component.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct PkgName(String);
 
 impl Display for PkgName {
@@ -18,17 +20,32 @@ impl From<String> for PkgName {
     }
 }
 
-impl Field for PkgName {
-    type Input = String;
+impl Field for Option<Vec<PkgName>> {
+    type Input = Vec<String>;
     type Error = PkgNameLoadError;
 
     const XPATH_EXPR: &'static str = "/component/pkgname/text()";
 
     fn construct(input: Self::Input) -> Result<Self, Self::Error> {
-        Ok(PkgName(input))
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let names: Vec<_> = input.iter().map(|name| name.trim().to_string()).collect();
+        if names.iter().any(|name| name.is_empty()) {
+            return Err(PkgNameLoadError);
+        }
+
+        Ok(Some(names.into_iter().map(PkgName).collect()))
     }
 }
 
 #[derive(Clone, Debug, Fail)]
 #[fail(display = "Failed to load `pkgname`")]
 pub struct PkgNameLoadError;
+
+impl Diagnose for PkgNameLoadError {
+    fn tag(&self) -> &'static str {
+        "pkgname-invalid"
+    }
+}
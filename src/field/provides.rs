@@ -0,0 +1,160 @@
+//! The public interfaces a component `<provides>`, such as media types and binaries.
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use sxd_xpath::nodeset::Node;
+use xpath_reader::{Error as XpathError, FromXml, Reader};
+
+use super::Field;
+use validate::Diagnose;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum ProvidedKind {
+    MediaType,
+    Library,
+    Binary,
+    Font,
+    Modalias,
+    Firmware,
+    Python2,
+    Python3,
+    DBus,
+    Id,
+}
+
+impl FromStr for ProvidedKind {
+    type Err = ParseError;
+
+    /// Parses the name of a `<provides>` child element.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mediatype" | "mimetype" => Ok(ProvidedKind::MediaType),
+            "library" => Ok(ProvidedKind::Library),
+            "binary" => Ok(ProvidedKind::Binary),
+            "font" => Ok(ProvidedKind::Font),
+            "modalias" => Ok(ProvidedKind::Modalias),
+            "firmware" => Ok(ProvidedKind::Firmware),
+            "python2" => Ok(ProvidedKind::Python2),
+            "python3" => Ok(ProvidedKind::Python3),
+            "dbus" => Ok(ProvidedKind::DBus),
+            "id" => Ok(ProvidedKind::Id),
+            _ => Err(ParseError::InvalidKind(s.to_string())),
+        }
+    }
+}
+
+impl Display for ProvidedKind {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            ProvidedKind::MediaType => fmt.write_str("mediatype"),
+            ProvidedKind::Library => fmt.write_str("library"),
+            ProvidedKind::Binary => fmt.write_str("binary"),
+            ProvidedKind::Font => fmt.write_str("font"),
+            ProvidedKind::Modalias => fmt.write_str("modalias"),
+            ProvidedKind::Firmware => fmt.write_str("firmware"),
+            ProvidedKind::Python2 => fmt.write_str("python2"),
+            ProvidedKind::Python3 => fmt.write_str("python3"),
+            ProvidedKind::DBus => fmt.write_str("dbus"),
+            ProvidedKind::Id => fmt.write_str("id"),
+        }
+    }
+}
+
+/// One provided item, e.g. the media type `image/png` or the binary `gimp`.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Provided {
+    kind: ProvidedKind,
+    value: String,
+}
+
+impl Provided {
+    pub fn new<S: Into<String>>(kind: ProvidedKind, value: S) -> Self {
+        Provided {
+            kind,
+            value: value.into(),
+        }
+    }
+
+    pub fn kind(&self) -> ProvidedKind {
+        self.kind
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+#[derive(Debug)]
+pub struct ProvidedInput {
+    element: String,
+    value: String,
+}
+
+impl FromXml for ProvidedInput {
+    fn from_xml<'d>(reader: &'d Reader<'d>) -> Result<Self, XpathError> {
+        let element = match reader.anchor_node() {
+            Some(Node::Element(element)) => element.name().local_part().to_string(),
+            _ => String::new(),
+        };
+
+        Ok(ProvidedInput {
+            element,
+            value: reader.read(".")?,
+        })
+    }
+}
+
+impl Field for Option<Vec<Provided>> {
+    type Input = Vec<ProvidedInput>;
+    type Error = ParseError;
+
+    /// Also reads the legacy top-level `<mimetypes>` list.
+    const XPATH_EXPR: &'static str = "/component/provides/* | /component/mimetypes/mimetype";
+
+    fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let mut provides: Vec<Provided> = Vec::new();
+        for item in input {
+            let kind = ProvidedKind::from_str(&item.element)?;
+            let value = item.value.trim();
+            if value.is_empty() {
+                return Err(ParseError::MissingValue(kind));
+            }
+
+            let provided = Provided::new(kind, value);
+            if !provides.contains(&provided) {
+                provides.push(provided);
+            }
+        }
+
+        Ok(Some(provides))
+    }
+}
+
+#[derive(Clone, Debug, Fail)]
+pub enum ParseError {
+    #[fail(display = "Unknown provided item `{}`", _0)]
+    InvalidKind(String),
+    #[fail(display = "Empty provided `{}`", _0)]
+    MissingValue(ProvidedKind),
+}
+
+impl Diagnose for ParseError {
+    fn tag(&self) -> &'static str {
+        match *self {
+            ParseError::InvalidKind(_) => "provides-item-invalid",
+            ParseError::MissingValue(_) => "provides-item-empty",
+        }
+    }
+
+    fn subject(&self) -> Option<&str> {
+        match *self {
+            ParseError::InvalidKind(ref element) => Some(element),
+            ParseError::MissingValue(_) => None,
+        }
+    }
+}
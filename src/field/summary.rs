@@ -1,34 +1,73 @@
 use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::Deref;
 
-use super::Field;
+use super::localized::Localized;
+use super::{Field, ParseOptions};
+use validate::Diagnose;
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct Summary(String);
+/// The translatable `<summary>` of a component.
+///
+/// Displays as the untranslated value, which may be missing if only translations were given.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Summary(Localized);
+
+impl Summary {
+    pub(crate) fn localized_mut(&mut self) -> &mut Localized {
+        let Summary(ref mut value) = *self;
+        value
+    }
+}
+
+impl Deref for Summary {
+    type Target = Localized;
+
+    fn deref(&self) -> &Localized {
+        let Summary(ref summary) = *self;
+        summary
+    }
+}
 
 impl Display for Summary {
     fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
-        let Summary(ref name) = *self;
-        name.fmt(fmt)
+        self.default_value().unwrap_or("").fmt(fmt)
     }
 }
 
 impl From<String> for Summary {
     fn from(s: String) -> Self {
-        Summary(s)
+        Summary(Localized::new(s))
     }
 }
 
 impl Field for Summary {
-    type Input = String;
+    type Input = Localized;
     type Error = SummaryLoadError;
 
-    const XPATH_EXPR: &'static str = "/component/summary/text()";
+    const XPATH_EXPR: &'static str = "/component/summary";
 
     fn construct(input: Self::Input) -> Result<Self, Self::Error> {
+        if input.is_empty() {
+            return Err(SummaryLoadError);
+        }
+
         Ok(Summary(input))
     }
+
+    fn construct_with(input: Self::Input, options: &ParseOptions) -> Result<Self, Self::Error> {
+        if options.is_partial() {
+            return Ok(Summary(input));
+        }
+
+        Self::construct(input)
+    }
 }
 
 #[derive(Clone, Debug, Fail)]
 #[fail(display = "Failed to load `summary`")]
 pub struct SummaryLoadError;
+
+impl Diagnose for SummaryLoadError {
+    fn tag(&self) -> &'static str {
+        "summary-invalid"
+    }
+}
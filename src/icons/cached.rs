@@ -0,0 +1,261 @@
+//! Icons shipped alongside catalogs, e.g. `/usr/share/swcatalog/icons/fedora/64x64/foo.png`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::is_file_name;
+use field::icon::Icon;
+
+lazy_static! {
+    static ref DEFAULT_DIRS: Vec<PathBuf> = vec![
+        PathBuf::from("usr/share/swcatalog/icons"),
+        PathBuf::from("usr/share/app-info/icons"),
+        PathBuf::from("var/lib/app-info/icons"),
+        PathBuf::from("var/cache/swcatalog/icons"),
+    ];
+}
+
+/// Resolves `Icon::Cached` names to files in the catalog icon directories.
+///
+/// Each directory has a subdirectory per catalog origin, containing the icons in `64x64` or
+/// `64x64@2` style subdirectories.
+#[derive(Clone, Debug)]
+pub struct CachedIcons {
+    root: PathBuf,
+    dirs: Vec<PathBuf>,
+}
+
+impl CachedIcons {
+    /// Searches the standard catalog icon directories below `/`.
+    pub fn new() -> Self {
+        CachedIcons {
+            root: PathBuf::from("/"),
+            dirs: DEFAULT_DIRS.clone(),
+        }
+    }
+
+    /// Searches below `root` instead of `/`.
+    pub fn with_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Replaces the directories which are searched, in order of preference.
+    pub fn with_dirs<I: IntoIterator<Item = P>, P: Into<PathBuf>>(mut self, dirs: I) -> Self {
+        self.dirs = dirs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn dirs(&self) -> &[PathBuf] {
+        self.dirs.as_slice()
+    }
+
+    /// Finds the file for the cached icon `name` of a catalog with `origin`, for display at
+    /// `size` logical pixels on a screen with `scale`.
+    ///
+    /// An exact size and scale is preferred. Otherwise the smallest icon which is at least as
+    /// large is used, so that it only has to be scaled down, and failing that the largest one.
+    pub fn resolve(&self, name: &str, origin: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        if !is_file_name(name) || !is_file_name(origin) {
+            return None;
+        }
+
+        let target = size.saturating_mul(scale);
+        let mut best: Option<(Candidate, PathBuf)> = None;
+
+        for dir in &self.dirs {
+            let origin_dir = self
+                .root
+                .join(dir.strip_prefix("/").unwrap_or(dir))
+                .join(origin);
+            let entries = match fs::read_dir(&origin_dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+
+            let mut size_dirs: Vec<_> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+            size_dirs.sort();
+            for size_dir in size_dirs {
+                let dir_name = size_dir.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                let (icon_size, icon_scale) = match parse_size_dir(dir_name) {
+                    Some(size) => size,
+                    None => continue,
+                };
+                // Directory names are untrusted, and too large a size is no use anyway.
+                let pixels = match icon_size.checked_mul(icon_scale) {
+                    Some(pixels) => pixels,
+                    None => continue,
+                };
+                let path = size_dir.join(name);
+                if !path.is_file() {
+                    continue;
+                }
+
+                let candidate = Candidate {
+                    exact: icon_size == size && icon_scale == scale,
+                    same_scale: icon_scale == scale,
+                    pixels,
+                };
+                let better = match best {
+                    Some((ref current, _)) => candidate.is_better_than(current, target),
+                    None => true,
+                };
+                if better {
+                    best = Some((candidate, path));
+                }
+            }
+        }
+
+        best.map(|(_, path)| path)
+    }
+
+    /// Finds the file for `icon` of a component from a catalog with `origin`, as `resolve` does.
+    ///
+    /// Only `Icon::Cached` icons are shipped with catalogs; other kinds yield `None`.
+    pub fn resolve_icon(
+        &self,
+        icon: &Icon,
+        origin: &str,
+        size: u32,
+        scale: u32,
+    ) -> Option<PathBuf> {
+        match *icon {
+            Icon::Cached { ref name } => self.resolve(name, origin, size, scale),
+            _ => None,
+        }
+    }
+}
+
+impl Default for CachedIcons {
+    fn default() -> Self {
+        CachedIcons::new()
+    }
+}
+
+/// A file found for the requested icon.
+struct Candidate {
+    exact: bool,
+    same_scale: bool,
+    pixels: u32,
+}
+
+impl Candidate {
+    /// Compares against the best file so far; on a tie the earlier file wins.
+    fn is_better_than(&self, other: &Candidate, target: u32) -> bool {
+        if self.exact != other.exact {
+            return self.exact;
+        }
+        if self.pixels == other.pixels {
+            return self.same_scale && !other.same_scale;
+        }
+
+        match (self.pixels >= target, other.pixels >= target) {
+            (true, true) => self.pixels < other.pixels,
+            (false, false) => self.pixels > other.pixels,
+            (large_enough, _) => large_enough,
+        }
+    }
+}
+
+/// Parses a `64x64` or `64x64@2` directory name into the size and scale.
+fn parse_size_dir(name: &str) -> Option<(u32, u32)> {
+    let mut parts = name.splitn(2, '@');
+    let dimensions = parts.next()?;
+    let scale = match parts.next() {
+        Some(scale) => scale.parse().ok()?,
+        None => 1,
+    };
+
+    let mut dimensions = dimensions.splitn(2, 'x');
+    let width: u32 = dimensions.next()?.parse().ok()?;
+    let height: u32 = dimensions.next()?.parse().ok()?;
+    if width != height || scale == 0 {
+        return None;
+    }
+
+    Some((width, scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use catalog::Components;
+    use test_util::TempDir;
+
+    #[test]
+    fn best_size_and_scale() {
+        let root = TempDir::new("cached-icons");
+        for dir in &["48x48", "64x64", "64x64@2", "128x128", "tiny"] {
+            let dir = Path::new("usr/share/swcatalog/icons/fedora").join(dir);
+            root.write(dir.join("foo.png"), b"");
+        }
+        // Its size in device pixels does not fit into a `u32`.
+        root.write(
+            "usr/share/swcatalog/icons/fedora/70000x70000@70000/foo.png",
+            b"",
+        );
+
+        let icons = CachedIcons::new().with_root(root.path());
+        let resolve = |size, scale| {
+            let path = icons.resolve("foo.png", "fedora", size, scale).unwrap();
+            let dir = path.parent().unwrap().file_name().unwrap();
+            dir.to_str().unwrap().to_string()
+        };
+
+        assert_eq!(resolve(64, 1), "64x64");
+        assert_eq!(resolve(64, 2), "64x64@2");
+        assert_eq!(resolve(96, 1), "128x128");
+        assert_eq!(resolve(48, 2), "64x64@2");
+        assert_eq!(resolve(32, 1), "48x48");
+        assert_eq!(resolve(256, 1), "128x128");
+        assert_eq!(resolve(u32::MAX, u32::MAX), "128x128");
+        assert!(icons.resolve("foo.png", "debian", 64, 1).is_none());
+        assert!(icons.resolve("bar.png", "fedora", 64, 1).is_none());
+
+        let catalog = "<components origin=\"fedora\"><component><id>org.example.Foo</id>\
+                       <name>Foo</name><summary>Does things</summary><pkgname>foo</pkgname>\
+                       <icon type=\"stock\">foo</icon>\
+                       <icon type=\"cached\" width=\"64\" height=\"64\">foo.png</icon>\
+                       </component></components>";
+        let component = Components::new(catalog.as_bytes())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let origin = component.origin().unwrap();
+        let found: Vec<_> = component
+            .icons()
+            .unwrap()
+            .iter()
+            .filter_map(|icon| icons.resolve_icon(icon, origin, 64, 1))
+            .collect();
+        assert_eq!(
+            found,
+            vec![root
+                .path()
+                .join("usr/share/swcatalog/icons/fedora/64x64/foo.png")]
+        );
+    }
+
+    #[test]
+    fn untrusted_names() {
+        let root = TempDir::new("cached-icons-names");
+        root.write("usr/share/swcatalog/icons/secret.png", b"");
+        root.write("usr/share/swcatalog/icons/fedora/64x64/secret.png", b"");
+
+        // Names from catalog data must not leave the icon directory.
+        let icons = CachedIcons::new().with_root(root.path());
+        let secret = root.path().join("usr/share/swcatalog/icons/secret.png");
+        for name in &["../../secret.png", secret.to_str().unwrap(), ".", ".."] {
+            assert!(icons.resolve(name, "fedora", 64, 1).is_none());
+        }
+        assert!(icons
+            .resolve("secret.png", "fedora/64x64/..", 64, 1)
+            .is_none());
+        assert!(icons.resolve("secret.png", "fedora", 64, 1).is_some());
+    }
+}
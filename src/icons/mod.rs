@@ -0,0 +1,13 @@
+//! Finds the files behind component icons.
+
+mod cached;
+mod theme;
+
+pub use self::cached::CachedIcons;
+pub use self::theme::{DirKind, IconTheme, IconThemes, ThemeDir, FALLBACK_THEME};
+
+/// Whether `name`, taken from catalog data, is a plain file name which cannot escape the
+/// directory it is joined onto.
+fn is_file_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains('/') && name != "." && name != ".."
+}
@@ -0,0 +1,530 @@
+//! Stock icon lookup in freedesktop.org icon themes, following the Icon Theme Specification.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::is_file_name;
+
+/// The theme every other theme falls back to.
+pub const FALLBACK_THEME: &str = "hicolor";
+
+const EXTENSIONS: &[&str] = &["png", "svg", "xpm"];
+
+/// How the icons in a theme directory may be scaled.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DirKind {
+    /// The icons are only used at exactly the directory's size.
+    Fixed,
+    /// The icons are used between `MinSize` and `MaxSize`.
+    Scalable,
+    /// The icons are used within `Threshold` pixels of the directory's size.
+    Threshold,
+}
+
+/// A directory of a theme, as described by its section in `index.theme`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ThemeDir {
+    path: String,
+    size: u32,
+    scale: u32,
+    context: Option<String>,
+    kind: DirKind,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+}
+
+impl ThemeDir {
+    fn from_section(path: &str, section: &BTreeMap<String, String>) -> Option<Self> {
+        let number = |key: &str| section.get(key).and_then(|v| v.trim().parse::<u32>().ok());
+        let size = number("Size")?;
+        let kind = match section.get("Type").map(|s| s.trim()) {
+            Some("Fixed") => DirKind::Fixed,
+            Some("Scalable") => DirKind::Scalable,
+            _ => DirKind::Threshold,
+        };
+
+        Some(ThemeDir {
+            path: path.to_string(),
+            size,
+            scale: number("Scale").unwrap_or(1),
+            context: section.get("Context").map(|s| s.trim().to_string()),
+            kind,
+            min_size: number("MinSize").unwrap_or(size),
+            max_size: number("MaxSize").unwrap_or(size),
+            threshold: number("Threshold").unwrap_or(2),
+        })
+    }
+
+    /// Returns the directory relative to the theme, e.g. `48x48/apps`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Returns the kind of icons in the directory, e.g. `Applications`.
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    pub fn kind(&self) -> DirKind {
+        self.kind
+    }
+
+    fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+
+        match self.kind {
+            DirKind::Fixed => self.size == size,
+            DirKind::Scalable => self.min_size <= size && size <= self.max_size,
+            DirKind::Threshold => {
+                self.size.saturating_sub(self.threshold) <= size
+                    && size <= self.size.saturating_add(self.threshold)
+            }
+        }
+    }
+
+    /// Returns how far the icons are from `size` at `scale`, in device pixels.
+    ///
+    /// The sizes come from `index.theme`, so the arithmetic saturates instead of overflowing.
+    fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let pixels = size.saturating_mul(scale);
+        let (min, max) = match self.kind {
+            DirKind::Fixed => (self.size, self.size),
+            DirKind::Scalable => (self.min_size, self.max_size),
+            DirKind::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size.saturating_add(self.threshold),
+            ),
+        };
+
+        let below = min.saturating_mul(self.scale).saturating_sub(pixels);
+        let above = pixels.saturating_sub(max.saturating_mul(self.scale));
+        below.max(above)
+    }
+}
+
+/// A parsed `index.theme`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct IconTheme {
+    name: String,
+    inherits: Vec<String>,
+    dirs: Vec<ThemeDir>,
+}
+
+impl IconTheme {
+    /// Parses the contents of the `index.theme` of the theme called `name`.
+    pub fn parse(name: &str, index: &str) -> Self {
+        let sections = parse_ini(index);
+        let empty = BTreeMap::new();
+        let header = sections.get("Icon Theme").unwrap_or(&empty);
+        let list = |key: &str| -> Vec<String> {
+            header
+                .get(key)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(|s| s.trim())
+                        .filter(|s| !s.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut paths = list("Directories");
+        for path in list("ScaledDirectories") {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+
+        let dirs = paths
+            .iter()
+            .filter_map(|path| ThemeDir::from_section(path, sections.get(path)?))
+            .collect();
+
+        IconTheme {
+            name: name.to_string(),
+            inherits: list("Inherits"),
+            dirs,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the themes to search when an icon is missing from this one.
+    pub fn inherits(&self) -> &[String] {
+        self.inherits.as_slice()
+    }
+
+    pub fn dirs(&self) -> &[ThemeDir] {
+        self.dirs.as_slice()
+    }
+}
+
+/// Looks up stock icons by name in the installed icon themes.
+///
+/// Themes are read from disk on every lookup.
+#[derive(Clone, Debug)]
+pub struct IconThemes {
+    theme: String,
+    dirs: Vec<PathBuf>,
+    pixmap_dirs: Vec<PathBuf>,
+}
+
+impl IconThemes {
+    /// Searches the `icons` directories of `$XDG_DATA_HOME` and `$XDG_DATA_DIRS` for icons of the
+    /// `hicolor` theme, then `/usr/share/pixmaps`.
+    pub fn new() -> Self {
+        let mut data_dirs = Vec::new();
+        match env::var_os("XDG_DATA_HOME") {
+            Some(dir) => data_dirs.push(PathBuf::from(dir)),
+            None => {
+                if let Some(home) = env::var_os("HOME") {
+                    data_dirs.push(PathBuf::from(home).join(".local/share"));
+                }
+            }
+        }
+        let system_dirs = env::var("XDG_DATA_DIRS")
+            .ok()
+            .filter(|dirs| !dirs.is_empty())
+            .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+        data_dirs.extend(system_dirs.split(':').map(PathBuf::from));
+
+        IconThemes {
+            theme: FALLBACK_THEME.to_string(),
+            dirs: data_dirs.into_iter().map(|dir| dir.join("icons")).collect(),
+            pixmap_dirs: vec![PathBuf::from("/usr/share/pixmaps")],
+        }
+    }
+
+    /// Looks icons up in the theme called `name` first, e.g. `Adwaita`.
+    pub fn with_theme<S: Into<String>>(mut self, name: S) -> Self {
+        self.theme = name.into();
+        self
+    }
+
+    /// Replaces the directories which contain the themes, in order of preference.
+    pub fn with_dirs<I: IntoIterator<Item = P>, P: Into<PathBuf>>(mut self, dirs: I) -> Self {
+        self.dirs = dirs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Replaces the directories of unthemed icons, which are searched last.
+    pub fn with_pixmap_dirs<I: IntoIterator<Item = P>, P: Into<PathBuf>>(
+        mut self,
+        dirs: I,
+    ) -> Self {
+        self.pixmap_dirs = dirs.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn theme(&self) -> &str {
+        &self.theme
+    }
+
+    pub fn dirs(&self) -> &[PathBuf] {
+        self.dirs.as_slice()
+    }
+
+    pub fn pixmap_dirs(&self) -> &[PathBuf] {
+        self.pixmap_dirs.as_slice()
+    }
+
+    /// Reads the `index.theme` of the theme called `name` from the first directory which has it.
+    pub fn load_theme(&self, name: &str) -> Option<IconTheme> {
+        self.dirs.iter().find_map(|dir| {
+            let index = fs::read_to_string(dir.join(name).join("index.theme")).ok()?;
+            Some(IconTheme::parse(name, &index))
+        })
+    }
+
+    /// Finds the file for the stock icon `name` at `size` and `scale`.
+    ///
+    /// The configured theme and the themes it inherits from are searched before `hicolor`, and
+    /// the pixmap directories last. If `context` is given, e.g. `Applications`, only theme
+    /// directories of that context are considered.
+    pub fn lookup(
+        &self,
+        name: &str,
+        size: u32,
+        scale: u32,
+        context: Option<&str>,
+    ) -> Option<PathBuf> {
+        let mut visited = Vec::new();
+        self.lookup_in(&self.theme, name, size, scale, context, &mut visited)
+            .or_else(|| self.lookup_in(FALLBACK_THEME, name, size, scale, context, &mut visited))
+            .or_else(|| self.pixmap_dirs.iter().find_map(|dir| find_file(dir, name)))
+    }
+
+    /// Searches `theme` and then its parents, depth first, skipping themes already visited.
+    fn lookup_in(
+        &self,
+        theme: &str,
+        name: &str,
+        size: u32,
+        scale: u32,
+        context: Option<&str>,
+        visited: &mut Vec<String>,
+    ) -> Option<PathBuf> {
+        if visited.iter().any(|t| t == theme) {
+            return None;
+        }
+        visited.push(theme.to_string());
+
+        let theme = self.load_theme(theme)?;
+        if let Some(path) = self.lookup_icon(&theme, name, size, scale, context) {
+            return Some(path);
+        }
+
+        theme
+            .inherits()
+            .iter()
+            .find_map(|parent| self.lookup_in(parent, name, size, scale, context, visited))
+    }
+
+    /// Finds the icon in a directory which matches the size, or else the closest size.
+    fn lookup_icon(
+        &self,
+        theme: &IconTheme,
+        name: &str,
+        size: u32,
+        scale: u32,
+        context: Option<&str>,
+    ) -> Option<PathBuf> {
+        let dirs: Vec<_> = theme
+            .dirs()
+            .iter()
+            .filter(|dir| context.is_none() || dir.context() == context)
+            .collect();
+        let files = |dir: &ThemeDir| -> Option<PathBuf> {
+            self.dirs
+                .iter()
+                .find_map(|base| find_file(&base.join(theme.name()).join(dir.path()), name))
+        };
+
+        if let Some(path) = dirs
+            .iter()
+            .filter(|dir| dir.matches_size(size, scale))
+            .find_map(|dir| files(dir))
+        {
+            return Some(path);
+        }
+
+        let mut closest: Option<(u32, PathBuf)> = None;
+        for dir in dirs {
+            let distance = dir.size_distance(size, scale);
+            let closer = match closest {
+                Some((best, _)) => distance < best,
+                None => true,
+            };
+            if closer {
+                if let Some(path) = files(dir) {
+                    closest = Some((distance, path));
+                }
+            }
+        }
+        closest.map(|(_, path)| path)
+    }
+}
+
+impl Default for IconThemes {
+    fn default() -> Self {
+        IconThemes::new()
+    }
+}
+
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    if !is_file_name(name) {
+        return None;
+    }
+
+    EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|path| path.is_file())
+}
+
+/// Parses a desktop-entry style file into its sections, ignoring localized keys.
+fn parse_ini(text: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut sections: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+    let mut current: Option<String> = None;
+
+    for line in text.lines().map(|l| l.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            let name = line[1..line.len() - 1].to_string();
+            sections.entry(name.clone()).or_default();
+            current = Some(name);
+        } else if let Some(i) = line.find('=') {
+            let key = line[..i].trim();
+            let section = current.as_ref().and_then(|name| sections.get_mut(name));
+            if let (Some(section), false) = (section, key.contains('[')) {
+                section.insert(key.to_string(), line[i + 1..].trim().to_string());
+            }
+        }
+    }
+
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::TempDir;
+
+    const HICOLOR: &str = "[Icon Theme]
+Name=Hicolor
+Directories=48x48/apps,scalable/apps,256x256/apps
+
+[48x48/apps]
+Size=48
+Context=Applications
+Type=Threshold
+
+[scalable/apps]
+Size=128
+MinSize=8
+MaxSize=512
+Context=Applications
+Type=Scalable
+
+[256x256/apps]
+Size=256
+Context=Applications
+Type=Fixed
+";
+
+    const CUSTOM: &str = "[Icon Theme]
+Name=Custom
+Name[de]=Eigenes
+Inherits=Loop
+Directories=32x32/apps,32x32@2/apps,32x32/devices
+ScaledDirectories=32x32@2/apps
+
+[32x32/apps]
+Size=32
+Context=Applications
+Type=Fixed
+
+[32x32@2/apps]
+Size=32
+Scale=2
+Context=Applications
+Type=Fixed
+
+[32x32/devices]
+Size=32
+Context=Devices
+Type=Fixed
+";
+
+    #[test]
+    fn parse_index() {
+        let theme = IconTheme::parse("Custom", CUSTOM);
+        assert_eq!(theme.inherits(), &["Loop".to_string()]);
+        assert_eq!(theme.dirs().len(), 3);
+        assert_eq!(theme.dirs()[1].scale(), 2);
+        assert_eq!(theme.dirs()[2].context(), Some("Devices"));
+    }
+
+    #[test]
+    fn huge_sizes_saturate() {
+        let theme = IconTheme::parse(
+            "Huge",
+            "[Icon Theme]\nDirectories=huge\n\n\
+             [huge]\nSize=4294967295\nScale=70000\nThreshold=70000\n",
+        );
+        let dir = &theme.dirs()[0];
+        assert!(dir.matches_size(u32::MAX, 70000));
+        assert!(!dir.matches_size(u32::MAX, 1));
+        assert_eq!(dir.size_distance(u32::MAX, 70000), 0);
+        assert_eq!(dir.size_distance(1, 1), u32::MAX - 1);
+    }
+
+    #[test]
+    fn lookup_with_inheritance_and_fallback() {
+        let root = TempDir::new("icon-theme");
+        let icons = root.path().join("usr/share/icons");
+        let write = |path: &str, data: &str| {
+            root.write(Path::new("usr/share/icons").join(path), data);
+        };
+
+        write("hicolor/index.theme", HICOLOR);
+        write("hicolor/48x48/apps/maps.png", "");
+        write("hicolor/256x256/apps/maps.png", "");
+        write("hicolor/scalable/apps/editor.svg", "");
+        write("Custom/index.theme", CUSTOM);
+        write("Custom/32x32/apps/maps.png", "");
+        write("Custom/32x32@2/apps/maps.png", "");
+        write("Custom/32x32/devices/phone.png", "");
+        // A theme which inherits from itself must not loop forever.
+        write("Loop/index.theme", "[Icon Theme]\nInherits=Loop,Custom\n");
+        root.write("usr/share/pixmaps/legacy.xpm", "");
+
+        let themes = IconThemes::new()
+            .with_theme("Custom")
+            .with_dirs(vec![icons.clone()])
+            .with_pixmap_dirs(vec![root.path().join("usr/share/pixmaps")]);
+        let lookup = |name, size, scale, context| {
+            let path = themes.lookup(name, size, scale, context)?;
+            Some(path.strip_prefix(&icons).unwrap_or(&path).to_path_buf())
+        };
+
+        assert_eq!(
+            lookup("maps", 32, 1, None),
+            Some("Custom/32x32/apps/maps.png".into())
+        );
+        assert_eq!(
+            lookup("maps", 32, 2, None),
+            Some("Custom/32x32@2/apps/maps.png".into())
+        );
+        // Closest size within the theme before falling back to hicolor.
+        assert_eq!(
+            lookup("maps", 48, 1, None),
+            Some("Custom/32x32/apps/maps.png".into())
+        );
+        assert_eq!(
+            lookup("editor", 64, 1, None),
+            Some("hicolor/scalable/apps/editor.svg".into())
+        );
+        assert_eq!(lookup("phone", 32, 1, Some("Applications")), None);
+        assert_eq!(
+            lookup("phone", 32, 1, Some("Devices")),
+            Some("Custom/32x32/devices/phone.png".into())
+        );
+
+        let hicolor = IconThemes::new().with_dirs(vec![icons.clone()]);
+        assert_eq!(
+            hicolor.lookup("maps", 200, 1, None),
+            Some(icons.join("hicolor/256x256/apps/maps.png"))
+        );
+        assert!(lookup("legacy", 48, 1, None)
+            .unwrap()
+            .ends_with("pixmaps/legacy.xpm"));
+
+        // Stock names from catalog data must not leave the icon directories.
+        root.write("usr/share/secret.png", "");
+        assert_eq!(lookup("../secret", 48, 1, None), None);
+        let secret = root.path().join("usr/share/secret");
+        assert_eq!(themes.lookup(secret.to_str().unwrap(), 48, 1, None), None);
+        assert_eq!(lookup("..", 48, 1, None), None);
+    }
+}
@@ -2,74 +2,178 @@
 
 #[macro_use]
 extern crate failure;
+extern crate flate2;
 #[macro_use]
 extern crate lazy_static;
 extern crate license_exprs;
+extern crate memmap2;
+extern crate quick_xml;
+#[cfg(feature = "parallel")]
+extern crate rayon;
 extern crate regex;
 #[macro_use]
 extern crate serde;
 extern crate serde_xml_rs;
 extern crate sxd_document;
-extern crate psl;
+extern crate sxd_xpath;
 extern crate url;
 extern crate xpath_reader;
+extern crate zstd;
 
+pub mod cache;
+pub mod catalog;
 pub mod comp_type;
 pub mod component;
+pub mod data_id;
 pub mod field;
+pub mod icons;
+pub mod location;
+pub mod lookup;
+pub mod menu;
 pub mod metainfo;
+pub mod pool;
+pub mod search;
+#[cfg(test)]
+mod test_util;
+pub mod validate;
 
-use xpath_reader::Reader;
-
+use comp_type::ComponentType;
+use field::agreement::{Agreement, AgreementKind};
+use field::bundle::Bundle;
 use field::category::Categories;
 use field::copyright::Copyright;
+use field::description::Description;
+use field::extends::Extends;
 use field::icon::Icon;
 use field::id::Id;
-use field::license::License;
+use field::keywords::Keywords;
+use field::license::{License, MetadataLicense};
+use field::merge::MergeKind;
 use field::name::Name;
 use field::pkg_name::PkgName;
+use field::provides::Provided;
 use field::summary::Summary;
-use field::Field;
+use field::{Field, ParseOptions};
+use location::Locator;
 use metainfo::ParseError;
+use validate::{Diagnose, Issue, Severity};
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct AppStream {
-    copyright: Copyright,
+    copyright: Option<Copyright>,
     id: Id,
-    pkg_name: PkgName,
+    kind: ComponentType,
+    pkg_names: Option<Vec<PkgName>>,
     name: Name,
     summary: Summary,
+    description: Option<Description>,
     license: Option<License>,
-    metadata_license: Option<License>,
+    metadata_license: Option<MetadataLicense>,
     icons: Option<Vec<Icon>>,
     categories: Option<Categories>,
+    keywords: Option<Keywords>,
+    agreements: Option<Vec<Agreement>>,
+    bundles: Option<Vec<Bundle>>,
+    provides: Option<Vec<Provided>>,
+    extends: Option<Extends>,
+    merge: Option<MergeKind>,
+    /// The `origin` of the catalog the component was read from.
+    origin: Option<String>,
 }
 
 impl AppStream {
-    pub(crate) fn parse<'d>(reader: Reader<'d>) -> Result<Self, ParseError> {
+    pub(crate) fn parse(locator: &Locator, options: &ParseOptions) -> Result<Self, ParseError> {
+        let merge = parse_field(locator, options)?;
+        let options = &partial_options(&merge, options);
+
         Ok(AppStream {
-            copyright: parse_field(&reader)?,
-            id: parse_field(&reader)?,
-            pkg_name: parse_field(&reader)?,
-            name: parse_field(&reader)?,
-            summary: parse_field(&reader)?,
-            license: parse_field(&reader)?,
-            metadata_license: parse_field(&reader)?,
-            icons: parse_field(&reader)?,
-            categories: parse_field(&reader)?,
+            copyright: parse_field(locator, options)?,
+            id: parse_field(locator, options)?,
+            kind: parse_field(locator, options)?,
+            pkg_names: parse_field(locator, options)?,
+            name: parse_field(locator, options)?,
+            summary: parse_field(locator, options)?,
+            description: parse_field(locator, options)?,
+            license: parse_field(locator, options)?,
+            metadata_license: parse_field(locator, options)?,
+            icons: parse_field(locator, options)?,
+            categories: parse_field(locator, options)?,
+            keywords: parse_field(locator, options)?,
+            agreements: parse_field(locator, options)?,
+            bundles: parse_field(locator, options)?,
+            provides: parse_field(locator, options)?,
+            extends: parse_field(locator, options)?,
+            merge,
+            origin: None,
+        })
+    }
+
+    /// Parses every field independently, collecting issues instead of stopping at the first.
+    pub(crate) fn parse_all(
+        locator: &Locator,
+        options: &ParseOptions,
+        issues: &mut Vec<Issue>,
+    ) -> Option<Self> {
+        let merge = collect_field(locator, options, issues);
+        let options = &partial_options(merge.as_ref().unwrap_or(&None), options);
+
+        let copyright = collect_field(locator, options, issues);
+        let id = collect_field(locator, options, issues);
+        let kind = collect_field(locator, options, issues);
+        let pkg_names = collect_field(locator, options, issues);
+        let name = collect_field(locator, options, issues);
+        let summary = collect_field(locator, options, issues);
+        let description = collect_field(locator, options, issues);
+        let license = collect_field(locator, options, issues);
+        let metadata_license = collect_field(locator, options, issues);
+        let icons = collect_field(locator, options, issues);
+        let categories = collect_field(locator, options, issues);
+        let keywords = collect_field(locator, options, issues);
+        let agreements = collect_field(locator, options, issues);
+        let bundles = collect_field(locator, options, issues);
+        let provides = collect_field(locator, options, issues);
+        let extends = collect_field(locator, options, issues);
+
+        Some(AppStream {
+            copyright: copyright?,
+            id: id?,
+            kind: kind?,
+            pkg_names: pkg_names?,
+            name: name?,
+            summary: summary?,
+            description: description?,
+            license: license?,
+            metadata_license: metadata_license?,
+            icons: icons?,
+            categories: categories?,
+            keywords: keywords?,
+            agreements: agreements?,
+            bundles: bundles?,
+            provides: provides?,
+            extends: extends?,
+            merge: merge?,
+            origin: None,
         })
     }
 
-    pub fn copyright(&self) -> &Copyright {
-        &self.copyright
+    pub fn copyright(&self) -> Option<&Copyright> {
+        self.copyright.as_ref()
     }
 
     pub fn id(&self) -> &Id {
         &self.id
     }
 
-    pub fn pkg_name(&self) -> &PkgName {
-        &self.pkg_name
+    pub fn kind(&self) -> &ComponentType {
+        &self.kind
+    }
+
+    pub fn pkg_names(&self) -> Option<&[PkgName]> {
+        self.pkg_names.as_deref()
+    }
+
+    pub fn bundles(&self) -> Option<&[Bundle]> {
+        self.bundles.as_deref()
     }
 
     pub fn name(&self) -> &Name {
@@ -80,26 +184,221 @@ impl AppStream {
         &self.summary
     }
 
+    pub fn description(&self) -> Option<&Description> {
+        self.description.as_ref()
+    }
+
     pub fn license(&self) -> Option<&License> {
         self.license.as_ref()
     }
 
     pub fn metadata_license(&self) -> Option<&License> {
-        self.metadata_license.as_ref()
+        self.metadata_license.as_deref()
     }
 
     pub fn icons(&self) -> Option<&[Icon]> {
-        self.icons.as_ref().map(|vec| vec.as_slice())
+        self.icons.as_deref()
     }
 
     pub fn categories(&self) -> Option<&Categories> {
         self.categories.as_ref()
     }
+
+    pub fn keywords(&self) -> Option<&Keywords> {
+        self.keywords.as_ref()
+    }
+
+    /// Returns the media types, binaries and other interfaces the component provides.
+    pub fn provides(&self) -> Option<&[Provided]> {
+        self.provides.as_deref()
+    }
+
+    /// Returns the IDs of the components this one extends, e.g. the application of an addon.
+    pub fn extends(&self) -> Option<&[Id]> {
+        self.extends.as_deref()
+    }
+
+    /// Returns the `origin` of the catalog the component was read from, e.g. `flathub`.
+    pub fn origin(&self) -> Option<&str> {
+        self.origin.as_deref()
+    }
+
+    pub(crate) fn set_origin(&mut self, origin: Option<String>) {
+        self.origin = origin;
+    }
+
+    /// Returns how this catalog entry amends the component with the same id, if it does.
+    pub fn merge_kind(&self) -> Option<MergeKind> {
+        self.merge
+    }
+
+    pub fn agreements(&self) -> Option<&[Agreement]> {
+        self.agreements.as_deref()
+    }
+
+    /// Returns the privacy policy which must be shown before first launch, if any.
+    pub fn privacy_policy(&self) -> Option<&Agreement> {
+        self.agreements().and_then(|agreements| {
+            agreements
+                .iter()
+                .find(|a| *a.kind() == AgreementKind::Privacy)
+        })
+    }
+
+    /// Lists every agreement section type declared by this component, without duplicates.
+    ///
+    /// For GDPR-style privacy policies these are values like `GDPR-data-collection`.
+    pub fn agreement_section_kinds(&self) -> Vec<&str> {
+        let mut kinds: Vec<&str> = Vec::new();
+        for agreement in self.agreements().unwrap_or(&[]) {
+            for kind in agreement.section_kinds() {
+                if !kinds.contains(&kind) {
+                    kinds.push(kind);
+                }
+            }
+        }
+        kinds
+    }
+
+    /// Fills the fields which are missing here from the same component of a lower-priority source.
+    pub(crate) fn fill_missing_from(&mut self, other: &AppStream) {
+        if self.kind == ComponentType::Generic {
+            self.kind = other.kind.clone();
+        }
+        self.name.localized_mut().fill_from(&other.name);
+        self.summary.localized_mut().fill_from(&other.summary);
+        fill(&mut self.copyright, &other.copyright);
+        fill(&mut self.pkg_names, &other.pkg_names);
+        fill(&mut self.description, &other.description);
+        fill(&mut self.keywords, &other.keywords);
+        fill(&mut self.provides, &other.provides);
+        fill(&mut self.extends, &other.extends);
+        fill(&mut self.license, &other.license);
+        fill(&mut self.metadata_license, &other.metadata_license);
+        fill(&mut self.icons, &other.icons);
+        fill(&mut self.categories, &other.categories);
+        fill(&mut self.agreements, &other.agreements);
+        fill(&mut self.bundles, &other.bundles);
+        fill(&mut self.origin, &other.origin);
+    }
+
+    /// Applies a catalog entry with `merge="append"` or `merge="replace"` to this component.
+    pub(crate) fn apply_merge(&mut self, other: &AppStream) {
+        match other.merge {
+            Some(MergeKind::Append) => {
+                self.name.localized_mut().fill_from(&other.name);
+                self.summary.localized_mut().fill_from(&other.summary);
+                fill(&mut self.copyright, &other.copyright);
+                fill(&mut self.keywords, &other.keywords);
+                fill(&mut self.extends, &other.extends);
+                fill(&mut self.license, &other.license);
+                fill(&mut self.metadata_license, &other.metadata_license);
+                append(&mut self.pkg_names, &other.pkg_names);
+                append(&mut self.icons, &other.icons);
+                append(&mut self.agreements, &other.agreements);
+                append(&mut self.bundles, &other.bundles);
+                append(&mut self.provides, &other.provides);
+                if let Some(ref description) = other.description {
+                    match self.description {
+                        Some(ref mut existing) => existing.localized_mut().fill_from(description),
+                        None => self.description = Some(description.clone()),
+                    }
+                }
+                if let Some(ref categories) = other.categories {
+                    match self.categories {
+                        Some(ref mut existing) => existing.append(categories),
+                        None => self.categories = Some(categories.clone()),
+                    }
+                }
+            }
+            Some(MergeKind::Replace) => {
+                if !other.name.is_empty() {
+                    self.name = other.name.clone();
+                }
+                if !other.summary.is_empty() {
+                    self.summary = other.summary.clone();
+                }
+                replace(&mut self.copyright, &other.copyright);
+                replace(&mut self.description, &other.description);
+                replace(&mut self.keywords, &other.keywords);
+                replace(&mut self.provides, &other.provides);
+                replace(&mut self.extends, &other.extends);
+                replace(&mut self.pkg_names, &other.pkg_names);
+                replace(&mut self.license, &other.license);
+                replace(&mut self.metadata_license, &other.metadata_license);
+                replace(&mut self.icons, &other.icons);
+                replace(&mut self.categories, &other.categories);
+                replace(&mut self.agreements, &other.agreements);
+                replace(&mut self.bundles, &other.bundles);
+            }
+            Some(MergeKind::RemoveComponent) | None => {}
+        }
+    }
+}
+
+/// Lets merge components omit required fields such as `<name>`.
+fn partial_options(merge: &Option<MergeKind>, options: &ParseOptions) -> ParseOptions {
+    let mut options = options.clone();
+    if merge.is_some() {
+        options.set_partial(true);
+    }
+    options
+}
+
+fn fill<T: Clone>(field: &mut Option<T>, other: &Option<T>) {
+    if field.is_none() {
+        *field = other.clone();
+    }
 }
 
-fn parse_field<'d, F: Field>(reader: &Reader<'d>) -> Result<F, ParseError> {
-    let input = F::load(&reader).map_err(ParseError::Xpath)?;
-    F::construct(input)
-        .map_err(|e| e.into())
-        .map_err(ParseError::FieldParseFail)
+fn replace<T: Clone>(field: &mut Option<T>, other: &Option<T>) {
+    if other.is_some() {
+        *field = other.clone();
+    }
+}
+
+fn append<T: Clone + PartialEq>(field: &mut Option<Vec<T>>, other: &Option<Vec<T>>) {
+    if let Some(ref other) = *other {
+        let values = field.get_or_insert_with(Vec::new);
+        for value in other {
+            if !values.contains(value) {
+                values.push(value.clone());
+            }
+        }
+    }
+}
+
+fn parse_field<F: Field>(locator: &Locator, options: &ParseOptions) -> Result<F, ParseError> {
+    let input = F::load(locator.reader()).map_err(|error| ParseError::Xpath {
+        location: locator.locate(F::XPATH_EXPR, None),
+        error,
+    })?;
+
+    F::construct_with(input, options).map_err(|e| ParseError::FieldParseFail {
+        location: locator.locate(F::XPATH_EXPR, e.subject()),
+        error: e.into(),
+    })
+}
+
+fn collect_field<F: Field>(
+    locator: &Locator,
+    options: &ParseOptions,
+    issues: &mut Vec<Issue>,
+) -> Option<F> {
+    let input = match F::load(locator.reader()) {
+        Ok(input) => input,
+        Err(e) => {
+            let explanation = format!("could not read `{}`: {}", F::XPATH_EXPR, e);
+            let issue = Issue::new(Severity::Error, "field-unreadable", explanation);
+            issues.push(issue.at(locator.locate(F::XPATH_EXPR, None)));
+            return None;
+        }
+    };
+
+    F::construct_with(input, options)
+        .map_err(|e| {
+            let location = locator.locate(F::XPATH_EXPR, e.subject());
+            issues.push(Issue::from_error(&e).at(location))
+        })
+        .ok()
 }
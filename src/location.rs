@@ -0,0 +1,400 @@
+//! Line and column positions of elements and attributes in the source text.
+//!
+//! `sxd_document` does not keep track of where nodes were declared, so the source text is
+//! scanned separately and nodes are matched up by their position in the element tree.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use sxd_xpath::nodeset::Node;
+use xpath_reader::Reader;
+
+/// A 1-based line and column in the source document.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Location {
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    pub fn new(line: usize, column: usize) -> Self {
+        Location { line, column }
+    }
+
+    /// Computes the location of the given byte offset into `text`.
+    pub fn from_offset(text: &str, offset: usize) -> Self {
+        let mut location = Location::new(1, 1);
+        for (i, ch) in text.char_indices() {
+            if i >= offset {
+                break;
+            }
+            location.advance(ch);
+        }
+        location
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Advances past `bytes` of UTF-8 text, counting characters rather than bytes.
+    pub(crate) fn advance_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if byte == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else if byte & 0xc0 != 0x80 {
+                self.column += 1;
+            }
+        }
+    }
+
+    fn advance(&mut self, ch: char) {
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        write!(fmt, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ElementPos {
+    location: Location,
+    attributes: Vec<(String, Location)>,
+    comments: Vec<Location>,
+}
+
+/// Maps nodes of a parsed document back to their source locations.
+///
+/// Elements are keyed by their path in the tree, i.e. the index among sibling elements at each
+/// level starting from the document root.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    elements: HashMap<Vec<usize>, ElementPos>,
+    root_comments: Vec<Location>,
+}
+
+impl SourceMap {
+    /// Scans `xml` for the locations of start tags, attributes and comments.
+    ///
+    /// Malformed input is scanned on a best-effort basis; the XML parser reports the real error.
+    pub fn scan(xml: &str) -> Self {
+        let mut map = SourceMap::default();
+        let mut scanner = Scanner::new(xml);
+        let mut path: Vec<usize> = Vec::new();
+        let mut next_child: Vec<usize> = vec![0];
+
+        while let Some(start) = scanner.skip_to('<') {
+            if scanner.eat("<!--") {
+                scanner.skip_past("-->");
+                if path.is_empty() {
+                    map.root_comments.push(start);
+                } else if let Some(parent) = map.elements.get_mut(&path) {
+                    parent.comments.push(start);
+                }
+            } else if scanner.eat("<![CDATA[") {
+                scanner.skip_past("]]>");
+            } else if scanner.eat("<?") {
+                scanner.skip_past("?>");
+            } else if scanner.eat("<!") {
+                scanner.skip_past(">");
+            } else if scanner.eat("</") {
+                scanner.skip_past(">");
+                path.pop();
+                next_child.pop();
+            } else {
+                scanner.bump();
+                let index = next_child.last().cloned().unwrap_or(0);
+                if let Some(last) = next_child.last_mut() {
+                    *last += 1;
+                }
+
+                path.push(index);
+                let (attributes, empty) = scanner.start_tag();
+                map.elements.insert(
+                    path.clone(),
+                    ElementPos {
+                        location: start,
+                        attributes,
+                        comments: Vec::new(),
+                    },
+                );
+
+                if empty {
+                    path.pop();
+                } else {
+                    next_child.push(0);
+                }
+            }
+        }
+
+        map
+    }
+
+    /// Returns the source location of `node`.
+    ///
+    /// Text nodes resolve to the location of their parent element.
+    pub fn locate(&self, node: Node) -> Option<Location> {
+        match node {
+            Node::Element(_) => self.element(node).map(|e| e.location),
+            Node::Attribute(attr) => {
+                let name = attr.name().local_part();
+                let parent = node.parent().and_then(|p| self.element(p))?;
+                parent
+                    .attributes
+                    .iter()
+                    .find(|(n, _)| n.rsplit(':').next() == Some(name))
+                    .map(|&(_, location)| location)
+            }
+            Node::Text(_) => node.parent().and_then(|p| self.locate(p)),
+            Node::Comment(_) => {
+                let index = node
+                    .preceding_siblings()
+                    .iter()
+                    .filter(|n| n.comment().is_some())
+                    .count();
+                let comments = match node.parent() {
+                    Some(Node::Root(_)) | None => &self.root_comments,
+                    Some(parent) => &self.element(parent)?.comments,
+                };
+                comments.get(index).cloned()
+            }
+            _ => None,
+        }
+    }
+
+    fn element(&self, node: Node) -> Option<&ElementPos> {
+        node.element()?;
+
+        let mut path = Vec::new();
+        let mut cur = node;
+        while let Some(parent) = cur.parent() {
+            let index = cur
+                .preceding_siblings()
+                .iter()
+                .filter(|n| n.element().is_some())
+                .count();
+            path.push(index);
+            cur = parent;
+        }
+
+        path.reverse();
+        self.elements.get(&path)
+    }
+}
+
+/// Resolves XPath expressions against a parsed document to source locations.
+pub(crate) struct Locator<'a, 'd: 'a> {
+    reader: &'a Reader<'d>,
+    map: &'a SourceMap,
+}
+
+impl<'a, 'd> Locator<'a, 'd> {
+    pub fn new(reader: &'a Reader<'d>, map: &'a SourceMap) -> Self {
+        Locator { reader, map }
+    }
+
+    pub fn reader(&self) -> &'a Reader<'d> {
+        self.reader
+    }
+
+    /// Finds the location of the node matched by `xpath` which `subject` refers to.
+    ///
+    /// A node whose trimmed text equals `subject` is preferred over one which merely contains
+    /// it, e.g. in a comment. Falls back to the first matching node, or the root element if
+    /// nothing matched.
+    pub fn locate(&self, xpath: &str, subject: Option<&str>) -> Option<Location> {
+        let matched = self.reader.with_nodeset_eval(xpath).ok()?;
+        let nodes = matched.anchor_nodeset().document_order();
+        let node = subject
+            .and_then(|s| {
+                nodes
+                    .iter()
+                    .find(|n| n.string_value().trim() == s)
+                    .or_else(|| nodes.iter().find(|n| n.string_value().contains(s)))
+            })
+            .or_else(|| nodes.first())
+            .cloned();
+
+        match node {
+            Some(node) => self.map.locate(node),
+            None => {
+                let root = self.reader.with_nodeset_eval("/*").ok()?;
+                let node = root.anchor_node()?;
+                self.map.locate(node)
+            }
+        }
+    }
+}
+
+struct Scanner<'a> {
+    text: &'a str,
+    offset: usize,
+    location: Location,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Scanner {
+            text,
+            offset: 0,
+            location: Location::new(1, 1),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.offset += ch.len_utf8();
+        self.location.advance(ch);
+        Some(ch)
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            for _ in prefix.chars() {
+                self.bump();
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Advances to the next `ch` and returns its location, without consuming it.
+    fn skip_to(&mut self, ch: char) -> Option<Location> {
+        while self.peek()? != ch {
+            self.bump();
+        }
+        Some(self.location)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(ch) = self.peek() {
+            if !ch.is_whitespace() {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    fn skip_past(&mut self, end: &str) {
+        while !self.rest().is_empty() && !self.eat(end) {
+            self.bump();
+        }
+    }
+
+    /// Consumes the remainder of a start tag, returning its attributes and whether it was empty.
+    fn start_tag(&mut self) -> (Vec<(String, Location)>, bool) {
+        let mut attributes = Vec::new();
+        self.take_name();
+
+        loop {
+            self.skip_whitespace();
+
+            match self.peek() {
+                None => return (attributes, false),
+                Some('>') => {
+                    self.bump();
+                    return (attributes, false);
+                }
+                Some('/') => {
+                    self.bump();
+                    let empty = self.eat(">");
+                    return (attributes, empty);
+                }
+                Some(_) => {
+                    let location = self.location;
+                    let name = self.take_name();
+                    if name.is_empty() {
+                        self.bump();
+                        continue;
+                    }
+
+                    attributes.push((name, location));
+                    self.skip_attribute_value();
+                }
+            }
+        }
+    }
+
+    fn take_name(&mut self) -> String {
+        let start = self.offset;
+        while let Some(ch) = self.peek() {
+            if ch.is_whitespace() || ch == '=' || ch == '>' || ch == '/' {
+                break;
+            }
+            self.bump();
+        }
+        self.text[start..self.offset].to_string()
+    }
+
+    fn skip_attribute_value(&mut self) {
+        self.skip_whitespace();
+
+        if !self.eat("=") {
+            return;
+        }
+
+        self.skip_whitespace();
+
+        if let Some(quote) = self.peek().filter(|&c| c == '"' || c == '\'') {
+            self.bump();
+            while let Some(c) = self.bump() {
+                if c == quote {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_positions() {
+        let xml = concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<!-- c -->\n",
+            "<a>\n",
+            "  <b x='1' y=\"2\"/>\n",
+            "  <c>t</c>\n",
+            "</a>"
+        );
+        let map = SourceMap::scan(xml);
+
+        assert_eq!(map.root_comments, vec![Location::new(2, 1)]);
+        assert_eq!(map.elements[&vec![0]].location, Location::new(3, 1));
+
+        let b = &map.elements[&vec![0, 0]];
+        assert_eq!(b.location, Location::new(4, 3));
+        assert_eq!(b.attributes[1], ("y".to_string(), Location::new(4, 12)));
+        assert_eq!(map.elements[&vec![0, 1]].location, Location::new(5, 3));
+    }
+
+    #[test]
+    fn offset_to_location() {
+        assert_eq!(Location::from_offset("ab\ncd", 4), Location::new(2, 2));
+    }
+}
@@ -0,0 +1,135 @@
+//! Indexes for answering which components provide a media type, binary, modalias or font.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use field::provides::ProvidedKind;
+use AppStream;
+
+/// Maps provided items to the indices of the components which provide them.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ProvidesIndex {
+    exact: BTreeMap<(ProvidedKind, String), BTreeSet<usize>>,
+    /// Modalias glob patterns, keyed by the literal text before the first wildcard.
+    modaliases: BTreeMap<String, Vec<(String, usize)>>,
+}
+
+impl ProvidesIndex {
+    pub(crate) fn new(components: &[AppStream]) -> Self {
+        let mut index = ProvidesIndex::default();
+        for (i, component) in components.iter().enumerate() {
+            for provided in component.provides().unwrap_or(&[]) {
+                let key = normalize(provided.kind(), provided.value());
+                if provided.kind() == ProvidedKind::Modalias && is_glob(&key) {
+                    let prefix = key[..key.find(is_wildcard).unwrap()].to_string();
+                    index.modaliases.entry(prefix).or_default().push((key, i));
+                } else {
+                    index
+                        .exact
+                        .entry((provided.kind(), key))
+                        .or_default()
+                        .insert(i);
+                }
+            }
+        }
+        index
+    }
+
+    /// Returns the indices of the components which provide `value`, in ascending order.
+    pub(crate) fn lookup(&self, kind: ProvidedKind, value: &str) -> Vec<usize> {
+        let key = normalize(kind, value);
+        let mut found = self
+            .exact
+            .get(&(kind, key.clone()))
+            .cloned()
+            .unwrap_or_default();
+
+        if kind == ProvidedKind::Modalias {
+            let prefixes = key
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(Some(key.len()))
+                .map(|end| &key[..end]);
+            for prefix in prefixes {
+                for &(ref pattern, i) in self.modaliases.get(prefix).into_iter().flatten() {
+                    if glob_match(pattern, &key) {
+                        found.insert(i);
+                    }
+                }
+            }
+        }
+
+        found.into_iter().collect()
+    }
+}
+
+/// Brings a provided value into the form used as index key.
+///
+/// Binaries and libraries are looked up by file name, so `/usr/bin/foo` finds `foo`. Media types
+/// and font names are case-insensitive.
+fn normalize(kind: ProvidedKind, value: &str) -> String {
+    let value = value.trim();
+    match kind {
+        ProvidedKind::Binary | ProvidedKind::Library => {
+            value.rsplit('/').next().unwrap_or(value).to_string()
+        }
+        ProvidedKind::MediaType | ProvidedKind::Font => value.to_lowercase(),
+        _ => value.to_string(),
+    }
+}
+
+fn is_wildcard(c: char) -> bool {
+    c == '*' || c == '?'
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(is_wildcard)
+}
+
+/// Matches `text` against a glob `pattern`, where `*` matches any run of characters and `?`
+/// matches a single character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // The position after the last `*`, and the text position it was tried against.
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(&'*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("usb:v1130p0202d*", "usb:v1130p0202d0100dc00"));
+        assert!(glob_match("pci:v*d*1234*", "pci:v00008086d00001234sv"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("usb:v1130p0202d*", "usb:v1130p0203d0100"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(!glob_match("abc", "abcd"));
+    }
+}
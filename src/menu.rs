@@ -0,0 +1,208 @@
+//! Builds a software-center menu tree out of component categories.
+//!
+//! Main categories form the top level of the tree, additional categories become subgroups of
+//! every related main category they are listed with, and components are the leaves. Groups are
+//! ordered as in the Desktop Menu Specification registry and leaves are ordered by name.
+
+use std::collections::BTreeMap;
+
+use field::category::{Category, CategoryKind};
+use AppStream;
+
+#[derive(Clone, Debug)]
+pub struct Menu<'a> {
+    groups: Vec<MenuGroup<'a>>,
+}
+
+impl<'a> Menu<'a> {
+    pub fn build<I>(components: I) -> Self
+    where
+        I: IntoIterator<Item = &'a AppStream>,
+    {
+        let mut groups: BTreeMap<usize, MenuGroup<'a>> = BTreeMap::new();
+
+        for component in components {
+            let categories = match component.categories() {
+                Some(categories) => categories,
+                None => continue,
+            };
+
+            let mains = categories.iter().filter(|c| c.kind() == CategoryKind::Main);
+            for main in mains {
+                let group = groups
+                    .entry(main.registry_index())
+                    .or_insert_with(|| MenuGroup::new(main.clone()));
+
+                let mut in_subgroup = false;
+                for additional in categories.iter() {
+                    let is_child = additional.kind() == CategoryKind::Additional
+                        && additional.related().contains(&main.to_string().as_str());
+                    if is_child {
+                        group.subgroup(additional).components.push(component);
+                        in_subgroup = true;
+                    }
+                }
+
+                if !in_subgroup {
+                    group.components.push(component);
+                }
+            }
+        }
+
+        let mut groups: Vec<_> = groups.into_values().collect();
+        for group in &mut groups {
+            sort_components(&mut group.components);
+            group.subgroups.sort_by_key(|sub| sub.category.registry_index());
+            for sub in &mut group.subgroups {
+                sort_components(&mut sub.components);
+            }
+        }
+
+        Menu { groups }
+    }
+
+    pub fn groups(&self) -> &[MenuGroup<'a>] {
+        self.groups.as_slice()
+    }
+}
+
+/// A main category at the top level of the menu.
+#[derive(Clone, Debug)]
+pub struct MenuGroup<'a> {
+    category: Category,
+    subgroups: Vec<MenuSubgroup<'a>>,
+    components: Vec<&'a AppStream>,
+}
+
+impl<'a> MenuGroup<'a> {
+    fn new(category: Category) -> Self {
+        MenuGroup {
+            category,
+            subgroups: Vec::new(),
+            components: Vec::new(),
+        }
+    }
+
+    fn subgroup(&mut self, category: &Category) -> &mut MenuSubgroup<'a> {
+        match self.subgroups.iter().position(|s| s.category == *category) {
+            Some(i) => &mut self.subgroups[i],
+            None => {
+                self.subgroups.push(MenuSubgroup {
+                    category: category.clone(),
+                    components: Vec::new(),
+                });
+                self.subgroups.last_mut().unwrap()
+            }
+        }
+    }
+
+    pub fn category(&self) -> &Category {
+        &self.category
+    }
+
+    pub fn label(&self) -> &str {
+        self.category.label()
+    }
+
+    /// Returns the label translated to `locale`; see `Category::label_for`.
+    pub fn label_for(&self, locale: &str) -> &str {
+        self.category.label_for(locale)
+    }
+
+    pub fn subgroups(&self) -> &[MenuSubgroup<'a>] {
+        self.subgroups.as_slice()
+    }
+
+    /// Components listed under this main category without any matching additional category.
+    pub fn components(&self) -> &[&'a AppStream] {
+        self.components.as_slice()
+    }
+}
+
+/// An additional category nested below a main category.
+#[derive(Clone, Debug)]
+pub struct MenuSubgroup<'a> {
+    category: Category,
+    components: Vec<&'a AppStream>,
+}
+
+impl<'a> MenuSubgroup<'a> {
+    pub fn category(&self) -> &Category {
+        &self.category
+    }
+
+    pub fn label(&self) -> &str {
+        self.category.label()
+    }
+
+    /// Returns the label translated to `locale`; see `Category::label_for`.
+    pub fn label_for(&self, locale: &str) -> &str {
+        self.category.label_for(locale)
+    }
+
+    pub fn components(&self) -> &[&'a AppStream] {
+        self.components.as_slice()
+    }
+}
+
+fn sort_components(components: &mut Vec<&AppStream>) {
+    components.sort_by_key(|c| (c.name().to_string().to_lowercase(), c.id().to_string()));
+    components.dedup_by(|a, b| a.id() == b.id());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metainfo::Metainfo;
+
+    fn component(id: &str, name: &str, categories: &[&str]) -> AppStream {
+        let categories: String = categories
+            .iter()
+            .map(|c| format!("<category>{}</category>", c))
+            .collect();
+        let xml = format!(
+            r#"<?xml version="1.0" encoding="utf-8" ?>
+            <!-- Copyright 2018 First Lastname <your@email.com> -->
+            <component>
+                <id>{}</id>
+                <name>{}</name>
+                <summary>Does something</summary>
+                <pkgname>blah</pkgname>
+                <categories>{}</categories>
+            </component>"#,
+            id, name, categories
+        );
+        Metainfo::from_str(xml).validate().expect("Failed to read metainfo")
+    }
+
+    #[test]
+    fn build_tree() {
+        let components = vec![
+            component("org.foo.Player", "Player", &["AudioVideo", "Audio", "Player"]),
+            component("org.foo.Editor", "Editor", &["Utility", "TextEditor"]),
+            component("org.foo.Calc", "Calc", &["Utility"]),
+            component("org.foo.Amp", "Amp", &["AudioVideo", "Player"]),
+        ];
+
+        let menu = Menu::build(&components);
+        let labels: Vec<_> = menu.groups().iter().map(|g| g.label()).collect();
+        assert_eq!(labels, vec!["Sound & Video", "Audio", "Accessories"]);
+        let labels: Vec<_> = menu.groups().iter().map(|g| g.label_for("de_DE")).collect();
+        assert_eq!(labels, vec!["Multimedia", "Audio", "Zubehör"]);
+        assert_eq!(menu.groups()[0].label_for("pt_BR"), "Sound & Video");
+
+        let av = &menu.groups()[0];
+        assert!(av.components().is_empty());
+        assert_eq!(av.subgroups()[0].label(), "Player");
+        let names: Vec<_> = av.subgroups()[0]
+            .components()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        assert_eq!(names, vec!["Amp", "Player"]);
+
+        let utility = &menu.groups()[2];
+        assert_eq!(utility.components()[0].name().to_string(), "Calc");
+        assert_eq!(utility.subgroups()[0].category().to_string(), "TextEditor");
+    }
+}
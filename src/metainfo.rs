@@ -1,42 +1,150 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
 use failure::Error;
-use sxd_document::parser::Error as XmlError;
+use sxd_document::parser::{parse as parse_xml, Error as XmlError};
 use xpath_reader::{Error as XpathError, Reader};
 
 use comp_type::{ComponentType, InvalidComponentType};
+use field::id::TldPolicy;
+use field::ParseOptions;
+use location::{Location, Locator, SourceMap};
+use validate::{self, Issue, Severity, Validation};
 use AppStream;
 
-pub struct Metainfo<'d>(Result<Reader<'d>, ParseError>);
+pub struct Metainfo<'d> {
+    reader: Result<Reader<'d>, ParseError>,
+    map: SourceMap,
+    options: ParseOptions,
+    path: Option<PathBuf>,
+}
 
 impl<'d> Metainfo<'d> {
     pub fn from_str<S: AsRef<str> + 'd>(xml: S) -> Self {
-        let trimmed = xml.as_ref().trim();
-        let read = Reader::from_str(trimmed, None).map_err(ParseError::Xpath);
-        Metainfo(read)
+        let text = xml.as_ref();
+        let trimmed = text.trim();
+        let leading = text.len() - text.trim_start().len();
+
+        let reader = Reader::from_str(trimmed, None).map_err(|error| match parse_xml(trimmed) {
+            Err((offset, errors)) => ParseError::InvalidXml {
+                location: Location::from_offset(text, leading + offset),
+                errors,
+            },
+            Ok(_) => ParseError::Xpath {
+                error,
+                location: None,
+            },
+        });
+
+        Metainfo {
+            reader,
+            map: SourceMap::scan(text),
+            options: ParseOptions::default(),
+            path: None,
+        }
+    }
+
+    /// Reads the metainfo file at `path`.
+    ///
+    /// `validate_all` additionally checks the file name and install location.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Metainfo<'static>> {
+        let path = path.as_ref();
+        let xml = fs::read_to_string(path)?;
+
+        let mut metainfo = Metainfo::from_str(xml);
+        metainfo.path = Some(path.to_path_buf());
+        Ok(metainfo)
+    }
+
+    /// Selects which top-level domains are accepted in the component ID.
+    ///
+    /// Defaults to `TldPolicy::Iana`, which does not depend on anything outside this crate.
+    pub fn with_tld_policy(mut self, policy: TldPolicy) -> Self {
+        self.options.set_tld_policy(policy);
+        self
+    }
+
+    /// Replaces all parser options, e.g. with those of the catalog the document came from.
+    pub(crate) fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
     }
 
     pub fn validate(self) -> Result<AppStream, ParseError> {
-        let Metainfo(inner) = self;
-        let reader = inner?;
-        AppStream::parse(reader)
+        let reader = self.reader?;
+        AppStream::parse(&Locator::new(&reader, &self.map), &self.options)
+    }
+
+    /// Validates the document, collecting every issue rather than stopping at the first.
+    ///
+    /// A best-effort `AppStream` is returned as part of the result if there were no errors.
+    pub fn validate_all(self) -> Validation {
+        let mut issues = Vec::new();
+
+        let appstream = match self.reader {
+            Ok(reader) => {
+                let locator = Locator::new(&reader, &self.map);
+                let appstream = AppStream::parse_all(&locator, &self.options, &mut issues);
+                if let Some(ref appstream) = appstream {
+                    validate::lint(appstream, &locator, &mut issues);
+                }
+                appstream
+            }
+            Err(e) => {
+                let issue = Issue::new(Severity::Error, "xml-invalid", e.to_string());
+                issues.push(issue.at(e.location()));
+                None
+            }
+        };
+
+        if let Some(ref path) = self.path {
+            validate::lint_path(path, appstream.as_ref().map(|a| a.id()), &mut issues);
+        }
+
+        Validation::new(issues, appstream)
     }
 }
 
 #[derive(Debug, Fail)]
 pub enum ParseError {
-    #[fail(display = "Xpath error: {}", _0)]
-    Xpath(#[cause] XpathError),
-    #[fail(display = "Failed to parse field: {}", _0)]
-    FieldParseFail(Error),
+    #[fail(display = "Xpath error: {}", error)]
+    Xpath {
+        #[cause]
+        error: XpathError,
+        location: Option<Location>,
+    },
+    #[fail(display = "Failed to parse field: {}", error)]
+    FieldParseFail {
+        error: Error,
+        location: Option<Location>,
+    },
     #[fail(display = "XML errors at location {}: {:?}", location, errors)]
     InvalidXml {
-        location: usize,
+        location: Location,
         errors: Vec<XmlError>,
     },
 }
 
+impl ParseError {
+    /// Returns the line and column of the element or attribute which caused the error.
+    pub fn location(&self) -> Option<Location> {
+        match *self {
+            ParseError::Xpath { location, .. } => location,
+            ParseError::FieldParseFail { location, .. } => location,
+            ParseError::InvalidXml { location, .. } => Some(location),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::path::PathBuf;
+
     use super::*;
+    use field::icon::Icon;
+    use field::provides::ProvidedKind;
+    use test_util::TempDir;
 
     const SIMPLE: &str = r#"
         <?xml version="1.0" encoding="utf-8" ?>
@@ -58,4 +166,393 @@ mod tests {
 
         println!("{:?}", thing);
     }
+
+    const AGREEMENT: &str = r#"
+        <?xml version="1.0" encoding="utf-8" ?>
+        <!-- Copyright 2018 First Lastname <your@email.com> -->
+        <component type="desktop-application">
+            <name>Package</name>
+            <id>org.foo.bar</id>
+            <summary>Does something amazing</summary>
+            <pkgname>blah</pkgname>
+            <agreement type="privacy" version_id="1.2.3">
+                <agreement_section type="GDPR-data-collection">
+                    <name>Data collection</name>
+                    <name xml:lang="de">Datenerhebung</name>
+                    <description>
+                        <p>We collect nothing.</p>
+                        <p xml:lang="de">Wir erheben nichts.</p>
+                        <p>Not even logs.</p>
+                    </description>
+                </agreement_section>
+                <agreement_section type="GDPR-data-retention">
+                    <name>Retention</name>
+                </agreement_section>
+            </agreement>
+        </component>
+    "#;
+
+    #[test]
+    fn parse_agreement() {
+        let metainfo = Metainfo::from_str(AGREEMENT);
+        let thing = metainfo.validate().expect("Failed to read metainfo");
+
+        let policy = thing.privacy_policy().expect("Missing privacy policy");
+        assert_eq!(policy.version_id(), Some("1.2.3"));
+
+        let section = &policy.sections()[0];
+        assert_eq!(section.name().default_value(), Some("Data collection"));
+        assert_eq!(section.name().get("de"), Some("Datenerhebung"));
+        assert_eq!(
+            section.description().default_value(),
+            Some("We collect nothing.\n\nNot even logs.")
+        );
+        assert_eq!(section.description().get("de"), Some("Wir erheben nichts."));
+
+        assert_eq!(
+            thing.agreement_section_kinds(),
+            vec!["GDPR-data-collection", "GDPR-data-retention"]
+        );
+    }
+
+    const MANY_ISSUES: &str = r#"
+        <?xml version="1.0" encoding="utf-8" ?>
+        <!-- Copyright 2018 First Lastname <your@email.com> -->
+        <component type="desktop-application">
+            <id>org.foo.b$r</id>
+            <summary>Does something amazing</summary>
+            <pkgname>blah</pkgname>
+            <categories>
+                <category>Bogus</category>
+            </categories>
+        </component>
+    "#;
+
+    #[test]
+    fn validate_collects_all_issues() {
+        let validation = Metainfo::from_str(MANY_ISSUES).validate_all();
+        let tags: Vec<_> = validation.issues().iter().map(|i| i.tag()).collect();
+
+        assert_eq!(
+            tags,
+            vec!["cid-invalid-character", "name-invalid", "category-invalid"]
+        );
+        assert!(validation.has_errors());
+        assert!(validation.appstream().is_none());
+    }
+
+    #[test]
+    fn validate_warnings_keep_result() {
+        let xml = SIMPLE.replace(
+            "</component>",
+            "<categories><category>Utility</category><category>X-Foo</category></categories></component>",
+        );
+        let validation = Metainfo::from_str(xml).validate_all();
+
+        assert_eq!(validation.issues().len(), 1);
+        assert_eq!(validation.issues()[0].severity(), Severity::Warning);
+        assert_eq!(validation.issues()[0].tag(), "category-name-vendor");
+        assert_eq!(validation.issues()[0].location().map(|l| l.line()), Some(11));
+        assert!(validation.appstream().is_some());
+
+        // `X-Foo` is part of `X-FooBar`, but must be located at its own element.
+        let xml = SIMPLE.replace(
+            "</component>",
+            "<categories>\n<category>X-FooBar</category>\n<category>X-Foo</category>\n\
+             </categories></component>",
+        );
+        let validation = Metainfo::from_str(xml).validate_all();
+        let mut lines: Vec<_> = validation
+            .issues()
+            .iter()
+            .map(|i| i.location().map(|l| l.line()))
+            .collect();
+        lines.sort();
+        assert_eq!(lines, vec![Some(12), Some(13)]);
+    }
+
+    #[test]
+    fn errors_have_locations() {
+        let xml = SIMPLE.replace(
+            "</component>",
+            "<categories>\n<category>Utility</category>\n<category>Bogus</category>\n</categories></component>",
+        );
+
+        let error = Metainfo::from_str(xml.as_str()).validate().unwrap_err();
+        assert_eq!(error.location(), Some(Location::new(13, 1)));
+
+        let validation = Metainfo::from_str(xml.as_str()).validate_all();
+        assert_eq!(validation.issues()[0].location(), Some(Location::new(13, 1)));
+
+        let error = Metainfo::from_str("\n<component>\n<id></component>")
+            .validate()
+            .unwrap_err();
+        assert_eq!(error.location().map(|l| l.line()), Some(3));
+    }
+
+    #[test]
+    fn tld_policy() {
+        let xml = SIMPLE.replace("org.foo.bar", "zzz.foo.bar");
+        assert!(Metainfo::from_str(xml.as_str()).validate().is_err());
+
+        let permissive = Metainfo::from_str(xml.as_str()).with_tld_policy(TldPolicy::Permissive);
+        assert!(permissive.validate().is_ok());
+
+        let custom = TldPolicy::custom(["zzz"]);
+        let metainfo = Metainfo::from_str(xml.as_str()).with_tld_policy(custom.clone());
+        assert!(metainfo.validate().is_ok());
+
+        let xml = SIMPLE.replace("org.foo.bar", "test.foo.bar");
+        assert!(Metainfo::from_str(xml.as_str()).with_tld_policy(custom).validate().is_ok());
+    }
+
+    #[test]
+    fn id_lints() {
+        let xml = SIMPLE.replace("org.foo.bar", "org.Foo.my-app.desktop");
+        let validation = Metainfo::from_str(xml).validate_all();
+        let tags: Vec<_> = validation.issues().iter().map(|i| i.tag()).collect();
+
+        assert_eq!(
+            tags,
+            vec![
+                "cid-contains-uppercase-letter",
+                "cid-contains-hyphen",
+                "cid-has-desktop-suffix",
+            ]
+        );
+        assert!(validation.appstream().is_some());
+    }
+
+    #[test]
+    fn metadata_license_must_be_permissive() {
+        let xml = SIMPLE.replace("MIT OR Apache-2.0", "MIT AND Apache-2.0");
+        let validation = Metainfo::from_str(xml).validate_all();
+        assert_eq!(validation.issues()[0].tag(), "metadata-license-invalid");
+        assert_eq!(validation.issues()[0].location().map(|l| l.line()), Some(9));
+        assert!(validation.appstream().is_none());
+
+        let xml = SIMPLE.replace("MIT OR Apache-2.0", "CC0-1.0 OR FSFAP AND GFDL-1.3-or-later");
+        let validation = Metainfo::from_str(xml).validate_all();
+        assert!(validation.issues().is_empty());
+    }
+
+    #[test]
+    fn copyright_is_optional() {
+        let xml = SIMPLE.replace(
+            "<!-- Copyright 2014-2018 First Lastname <your@email.com>, Blah <thing@blah.org> -->",
+            "",
+        );
+        let thing = Metainfo::from_str(xml).validate().expect("Failed to read metainfo");
+        assert!(thing.copyright().is_none());
+
+        let thing = Metainfo::from_str(SIMPLE).validate().expect("Failed to read metainfo");
+        let statements = thing.copyright().map(|c| c.statements()).unwrap_or(&[]);
+        assert_eq!(statements[0].years(), Some("2014-2018"));
+
+        let xml = SIMPLE.replace(
+            "Copyright 2014-2018 First Lastname <your@email.com>, Blah <thing@blah.org>",
+            "Copyright 2018",
+        );
+        let validation = Metainfo::from_str(xml).validate_all();
+        assert_eq!(validation.issues()[0].tag(), "copyright-invalid");
+        assert_eq!(validation.issues()[0].severity(), Severity::Warning);
+        assert_eq!(validation.issues()[0].location().map(|l| l.line()), Some(3));
+        assert!(validation.appstream().is_some());
+
+        let xml = SIMPLE.replace(
+            "Copyright 2014-2018 First Lastname <your@email.com>, Blah <thing@blah.org>",
+            "SPDX-License-Identifier: Bogus",
+        );
+        let validation = Metainfo::from_str(xml).validate_all();
+        assert_eq!(validation.issues()[0].tag(), "copyright-spdx-license-invalid");
+        assert_eq!(validation.issues()[0].severity(), Severity::Warning);
+        assert!(validation.appstream().is_some());
+    }
+
+    #[test]
+    fn name_and_summary_lints() {
+        let xml = SIMPLE
+            .replace(
+                "<name>Package</name>",
+                "<name xml:lang=\"de\">Paket https://example.org </name>",
+            )
+            .replace(
+                "<summary>Does something amazing</summary>",
+                "<summary>Package does <b>something</b> really quite amazing.</summary>",
+            );
+
+        let validation = Metainfo::from_str(xml).validate_all();
+        let tags: Vec<_> = validation.issues().iter().map(|i| i.tag()).collect();
+
+        assert_eq!(
+            tags,
+            vec![
+                "name-has-trailing-whitespace",
+                "name-has-url",
+                "name-missing-untranslated",
+                "summary-too-long",
+                "summary-has-dot-suffix",
+                "summary-has-markup",
+            ]
+        );
+        assert!(validation.appstream().is_some());
+
+        let xml = SIMPLE.replace("Does something amazing", "The package manager");
+        let validation = Metainfo::from_str(xml).validate_all();
+        assert_eq!(validation.issues()[0].tag(), "summary-repeats-name");
+
+        let xml = SIMPLE.replace("Does something amazing", "Repackages archives");
+        let validation = Metainfo::from_str(xml).validate_all();
+        assert!(validation.issues().is_empty());
+
+        // Indentation around a name on its own line is formatting, not trailing whitespace.
+        let xml = SIMPLE.replace(
+            "<name>Package</name>",
+            "<name>\n                Package\n            </name>",
+        );
+        let validation = Metainfo::from_str(xml).validate_all();
+        assert!(validation.issues().is_empty());
+    }
+
+    #[test]
+    fn pkgnames_and_bundles() {
+        let xml = SIMPLE.replace(
+            "<pkgname>blah</pkgname>",
+            "<pkgname>blah</pkgname><pkgname>\n  blah-data\n</pkgname>",
+        );
+        let thing = Metainfo::from_str(xml).validate().expect("Failed to read metainfo");
+        let names: Vec<_> = thing.pkg_names().unwrap().iter().map(|p| p.to_string()).collect();
+        assert_eq!(names, vec!["blah", "blah-data"]);
+        assert!(validate::catalog_issues(&thing).is_empty());
+
+        let xml = SIMPLE.replace("<pkgname>blah</pkgname>", "");
+        let thing = Metainfo::from_str(xml.as_str()).validate().expect("Failed to read metainfo");
+        assert!(thing.pkg_names().is_none());
+        assert_eq!(
+            validate::catalog_issues(&thing)[0].tag(),
+            "catalog-component-not-installable"
+        );
+
+        let xml = xml.replace(
+            "</component>",
+            "<bundle type=\"flatpak\">app/org.foo.bar/x86_64/stable</bundle></component>",
+        );
+        let thing = Metainfo::from_str(xml).validate().expect("Failed to read metainfo");
+        assert_eq!(thing.bundles().unwrap()[0].id(), "app/org.foo.bar/x86_64/stable");
+        assert!(validate::catalog_issues(&thing).is_empty());
+    }
+
+    #[test]
+    fn parse_icons() {
+        let thing = Metainfo::from_str(SIMPLE).validate().expect("Failed to read metainfo");
+        assert_eq!(
+            thing.icons().unwrap(),
+            &[Icon::Local {
+                path: PathBuf::from("/usr/share/icon.png"),
+                width: None,
+                height: None,
+            }]
+        );
+
+        let xml = SIMPLE.replace(
+            "<icon type=\"local\">/usr/share/icon.png</icon>",
+            "<icon type=\"stock\">foo</icon><icon type=\"cached\">foo.png</icon>\
+             <icon type=\"remote\" width=\"64\" height=\"64\">\
+             https://example.org/foo.png</icon>",
+        );
+        let thing = Metainfo::from_str(xml).validate().expect("Failed to read metainfo");
+        let icons = thing.icons().unwrap();
+        assert_eq!(icons.len(), 3);
+        assert_eq!(icons[0], Icon::Stock { id: "foo".into() });
+        assert_eq!(icons[1], Icon::Cached { name: "foo.png".into() });
+        match icons[2] {
+            Icon::Remote {
+                ref url,
+                width,
+                height,
+            } => {
+                assert_eq!(url.as_str(), "https://example.org/foo.png");
+                assert_eq!((width, height), (Some(64), Some(64)));
+            }
+            ref icon => panic!("Unexpected icon {:?}", icon),
+        }
+
+        let tag = |icon: &str| {
+            let xml = SIMPLE.replace("<icon type=\"local\">/usr/share/icon.png</icon>", icon);
+            let validation = Metainfo::from_str(xml).validate_all();
+            validation.issues()[0].tag()
+        };
+        assert_eq!(tag("<icon>foo</icon>"), "icon-type-missing");
+        assert_eq!(tag("<icon type=\"theme\">foo</icon>"), "icon-type-invalid");
+        assert_eq!(tag("<icon type=\"stock\"> </icon>"), "icon-value-missing");
+        assert_eq!(
+            tag("<icon type=\"local\" width=\"big\">/foo.png</icon>"),
+            "icon-size-invalid"
+        );
+        assert_eq!(tag("<icon type=\"remote\">foo.png</icon>"), "icon-url-invalid");
+    }
+
+    #[test]
+    fn description_keywords_provides() {
+        let xml = SIMPLE.replace(
+            "</component>",
+            r#"<description>
+                 <p>First.</p><p xml:lang="de">Erstens.</p>
+                 <ul><li>Item</li></ul>
+               </description>
+               <keywords><keyword>foo</keyword><keyword xml:lang="de">Ding</keyword></keywords>
+               <provides><mediatype>image/png</mediatype><binary>bar</binary></provides>
+               <mimetypes><mimetype>image/png</mimetype></mimetypes>
+               </component>"#,
+        );
+        let thing = Metainfo::from_str(xml).validate().expect("Failed to read metainfo");
+
+        let description = thing.description().unwrap();
+        assert_eq!(description.default_value(), Some("First.\n\nItem"));
+        assert_eq!(description.get("de_AT"), Some("Erstens."));
+
+        let keywords = thing.keywords().unwrap();
+        assert_eq!(keywords.default_values(), &["foo".to_string()]);
+        assert_eq!(keywords.get("de"), &["Ding".to_string()]);
+
+        let provides = thing.provides().unwrap();
+        assert_eq!(provides.len(), 2);
+        assert_eq!(provides[0].kind(), ProvidedKind::MediaType);
+        assert_eq!(provides[1].value(), "bar");
+
+        let xml = SIMPLE.replace("</component>", "<provides><thing/></provides></component>");
+        let validation = Metainfo::from_str(xml).validate_all();
+        let tags: Vec<_> = validation.issues().iter().map(|i| i.tag()).collect();
+        assert!(tags.contains(&"provides-item-invalid"));
+    }
+
+    fn path_tags(dir: &str, file_name: &str) -> Vec<&'static str> {
+        let root = TempDir::new("path");
+        let path = root.write(Path::new(dir).join(file_name), SIMPLE);
+
+        let validation = Metainfo::from_path(&path).unwrap().validate_all();
+        validation
+            .issues()
+            .iter()
+            .map(|i| i.tag())
+            .filter(|t| t.starts_with("metainfo-"))
+            .collect()
+    }
+
+    #[test]
+    fn metainfo_path() {
+        assert!(path_tags("usr/share/metainfo", "org.foo.bar.metainfo.xml").is_empty());
+        assert_eq!(
+            path_tags("usr/share/appdata", "org.foo.bar.appdata.xml"),
+            vec!["metainfo-legacy-suffix", "metainfo-legacy-path"]
+        );
+        assert_eq!(
+            path_tags("data", "org.foo.baz.metainfo.xml"),
+            vec!["metainfo-path-unexpected", "metainfo-filename-cid-mismatch"]
+        );
+        assert_eq!(
+            path_tags("usr/share/metainfo", "org.foo.bar.xml"),
+            vec!["metainfo-filename-invalid"]
+        );
+    }
 }
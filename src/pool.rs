@@ -0,0 +1,1052 @@
+//! Discovers and loads the metainfo files and catalogs installed on a system.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use catalog::{CatalogError, Components};
+use comp_type::ComponentType;
+use data_id::{DataId, WILDCARD};
+use field::id::{Id, TldPolicy};
+use field::merge::MergeKind;
+use field::provides::ProvidedKind;
+use field::ParseOptions;
+use icons::CachedIcons;
+use lookup::ProvidesIndex;
+use metainfo::{Metainfo, ParseError};
+use search::{self, SearchMatch};
+use validate::{Issue, Severity};
+use AppStream;
+
+const METAINFO_SUFFIXES: &[&str] = &[".metainfo.xml", ".appdata.xml"];
+const XML_SUFFIXES: &[&str] = &[".xml", ".xml.gz", ".xml.zst"];
+const YAML_SUFFIXES: &[&str] = &[".yml", ".yml.gz", ".yml.xz", ".yml.zst"];
+/// The priority of installed metainfo files, below the default catalog priority of 0 so that
+/// catalog data wins and metainfo files only fill in what the catalogs lack.
+const METAINFO_PRIORITY: i32 = -1;
+/// Files in each `<remote>/<arch>/active` directory of a flatpak installation, by preference.
+const FLATPAK_CATALOGS: &[&str] = &["appstream.xml.gz", "appstream.xml"];
+
+/// How the files in a metadata directory are laid out.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DirKind {
+    /// Metainfo files installed by individual packages, e.g. `/usr/share/metainfo`.
+    Metainfo,
+    /// Catalogs, e.g. `/usr/share/swcatalog/xml` or `/usr/share/swcatalog/yaml`.
+    Catalog,
+    /// A flatpak installation's `appstream` directory, with a catalog per remote and architecture.
+    Flatpak,
+}
+
+/// Whether metadata is installed for the whole system or for the current user only.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Scope {
+    System,
+    User,
+}
+
+impl Scope {
+    /// Returns the name used in data IDs, `system` or `user`.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Scope::System => "system",
+            Scope::User => "user",
+        }
+    }
+}
+
+/// A directory searched for metadata.
+///
+/// System directories are relative to the pool's root, user directories to the home directory.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PoolDir {
+    path: PathBuf,
+    kind: DirKind,
+    scope: Scope,
+}
+
+impl PoolDir {
+    pub fn new<P: Into<PathBuf>>(path: P, kind: DirKind) -> Self {
+        PoolDir {
+            path: path.into(),
+            kind,
+            scope: Scope::System,
+        }
+    }
+
+    /// Creates a directory below the user's home directory, e.g. `.local/share/flatpak`.
+    pub fn user<P: Into<PathBuf>>(path: P, kind: DirKind) -> Self {
+        PoolDir {
+            scope: Scope::User,
+            ..PoolDir::new(path, kind)
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn kind(&self) -> DirKind {
+        self.kind
+    }
+
+    pub fn scope(&self) -> Scope {
+        self.scope
+    }
+}
+
+lazy_static! {
+    /// The directories searched by default.
+    static ref DEFAULT_DIRS: Vec<PoolDir> = vec![
+        PoolDir::new("usr/share/metainfo", DirKind::Metainfo),
+        PoolDir::new("usr/share/appdata", DirKind::Metainfo),
+        PoolDir::new("usr/share/swcatalog/xml", DirKind::Catalog),
+        PoolDir::new("usr/share/swcatalog/yaml", DirKind::Catalog),
+        PoolDir::new("var/lib/app-info/xmls", DirKind::Catalog),
+        PoolDir::new("var/lib/app-info/yaml", DirKind::Catalog),
+        PoolDir::new("var/cache/swcatalog/xml", DirKind::Catalog),
+        PoolDir::new("var/cache/swcatalog/yaml", DirKind::Catalog),
+        PoolDir::new("var/lib/flatpak/appstream", DirKind::Flatpak),
+        PoolDir::user(".local/share/flatpak/appstream", DirKind::Flatpak),
+    ];
+}
+
+/// The format of a discovered metadata file.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SourceKind {
+    Metainfo,
+    XmlCatalog,
+    /// A DEP-11 YAML catalog, as used by Debian and Ubuntu. These cannot be read yet, and are
+    /// reported as unsupported when loading.
+    YamlCatalog,
+}
+
+/// A metadata file found by a `Pool`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Source {
+    path: PathBuf,
+    kind: SourceKind,
+    scope: Scope,
+}
+
+impl Source {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn kind(&self) -> SourceKind {
+        self.kind
+    }
+
+    /// Returns the scope of the directory the file was found in.
+    pub fn scope(&self) -> Scope {
+        self.scope
+    }
+}
+
+/// Every component found in the metadata directories of a system.
+///
+/// Components with the same data ID are merged: the data from the source with the highest
+/// priority is kept, and its missing fields are filled in from the other sources. Components
+/// without an origin, such as installed metainfo files, are merged into every component with
+/// their id. Catalog entries with a `merge` attribute are applied afterwards, in ascending
+/// priority.
+#[derive(Clone, Debug)]
+pub struct Pool {
+    root: PathBuf,
+    home: Option<PathBuf>,
+    dirs: Vec<PoolDir>,
+    locale: Option<String>,
+    options: ParseOptions,
+    components: Vec<AppStream>,
+    /// The data ID of each component, by index.
+    data_ids: Vec<DataId>,
+    provides: ProvidesIndex,
+    /// The indices of the addons of each component, by the parent's ID.
+    addons: BTreeMap<String, Vec<usize>>,
+    issues: Vec<Issue>,
+}
+
+impl Pool {
+    /// Creates an empty pool which searches the standard directories below `/`, using the
+    /// home directory and locale of the environment.
+    pub fn new() -> Self {
+        Pool {
+            root: PathBuf::from("/"),
+            home: env::var_os("HOME").map(PathBuf::from),
+            dirs: DEFAULT_DIRS.clone(),
+            locale: env_locale(),
+            options: ParseOptions::default(),
+            components: Vec::new(),
+            data_ids: Vec::new(),
+            provides: ProvidesIndex::default(),
+            addons: BTreeMap::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    /// Searches below `root` instead of `/`, e.g. to load a chroot or test fixtures.
+    ///
+    /// The home directory is looked up below `root` as well.
+    pub fn with_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Sets the home directory whose user directories are searched, e.g. `/home/jane`; `None`
+    /// only searches system directories.
+    pub fn with_home<P: Into<PathBuf>>(mut self, home: Option<P>) -> Self {
+        self.home = home.map(Into::into);
+        self
+    }
+
+    /// Replaces the directories which are searched.
+    pub fn with_dirs<I: IntoIterator<Item = PoolDir>>(mut self, dirs: I) -> Self {
+        self.dirs = dirs.into_iter().collect();
+        self
+    }
+
+    /// Sets the locale whose translations are searched, e.g. `de_DE`; `None` uses the
+    /// untranslated text.
+    pub fn with_locale<S: Into<String>>(mut self, locale: Option<S>) -> Self {
+        self.locale = locale.map(Into::into);
+        self
+    }
+
+    /// Selects which top-level domains are accepted in component IDs, as
+    /// `Metainfo::with_tld_policy` does.
+    pub fn with_tld_policy(mut self, policy: TldPolicy) -> Self {
+        self.options.set_tld_policy(policy);
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn home(&self) -> Option<&Path> {
+        self.home.as_deref()
+    }
+
+    pub fn dirs(&self) -> &[PoolDir] {
+        self.dirs.as_slice()
+    }
+
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// Lists the metadata files in the search directories, in a stable order.
+    ///
+    /// Missing directories are skipped, and directories which cannot be read are returned as
+    /// errors next to the sources found in the others.
+    pub fn sources(&self) -> (Vec<Source>, Vec<PoolError>) {
+        let mut sources = Vec::new();
+        let mut errors = Vec::new();
+        for dir in &self.dirs {
+            let path = match dir.scope {
+                Scope::System => self.root.join(strip_root(&dir.path)),
+                Scope::User => match self.home {
+                    Some(ref home) => self.root.join(strip_root(home)).join(&dir.path),
+                    None => continue,
+                },
+            };
+            let source = |path, kind| Source {
+                path,
+                kind,
+                scope: dir.scope,
+            };
+
+            match dir.kind {
+                DirKind::Metainfo => {
+                    for file in list_dir(&path, &mut errors) {
+                        if has_suffix(&file, METAINFO_SUFFIXES) {
+                            sources.push(source(file, SourceKind::Metainfo));
+                        }
+                    }
+                }
+                DirKind::Catalog => {
+                    for file in list_dir(&path, &mut errors) {
+                        if has_suffix(&file, XML_SUFFIXES) {
+                            sources.push(source(file, SourceKind::XmlCatalog));
+                        } else if has_suffix(&file, YAML_SUFFIXES) {
+                            sources.push(source(file, SourceKind::YamlCatalog));
+                        }
+                    }
+                }
+                DirKind::Flatpak => {
+                    for remote in list_dir(&path, &mut errors) {
+                        for arch in list_dir(&remote, &mut errors) {
+                            let active = arch.join("active");
+                            let file = FLATPAK_CATALOGS
+                                .iter()
+                                .map(|name| active.join(name))
+                                .find(|file| file.is_file());
+                            if let Some(file) = file {
+                                sources.push(source(file, SourceKind::XmlCatalog));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        (sources, errors)
+    }
+
+    /// Loads every component from the search directories, replacing what was loaded before.
+    ///
+    /// Directories, sources and components which fail to load are skipped and returned as
+    /// errors, so that one broken file does not hide the rest of the system.
+    pub fn load(&mut self) -> Vec<PoolError> {
+        let (sources, mut errors) = self.sources();
+
+        let mut entries = Vec::new();
+        for source in sources {
+            load_source(&source, &self.options, &mut entries, &mut errors);
+        }
+
+        let mut paths = Vec::new();
+        self.data_ids.clear();
+        self.components.clear();
+        for (data_id, path, component) in merge_entries(entries) {
+            self.data_ids.push(data_id);
+            paths.push(path);
+            self.components.push(component);
+        }
+        self.provides = ProvidesIndex::new(&self.components);
+        let (addons, issues) = link_addons(&self.components, &paths);
+        self.addons = addons;
+        self.issues = issues;
+        errors
+    }
+
+    /// Returns the merged components, sorted by id and then by data ID.
+    pub fn components(&self) -> &[AppStream] {
+        self.components.as_slice()
+    }
+
+    /// Returns a resolver for the cached icons of the catalogs below the pool's root.
+    pub fn cached_icons(&self) -> CachedIcons {
+        CachedIcons::new().with_root(&self.root)
+    }
+
+    /// Returns the data IDs of the components, in the same order as `components()`.
+    pub fn data_ids(&self) -> &[DataId] {
+        self.data_ids.as_slice()
+    }
+
+    /// Returns the components whose data ID matches `data_id`, which may contain wildcards.
+    pub fn by_data_id(&self, data_id: &DataId) -> Vec<&AppStream> {
+        let candidates = if data_id.id() == WILDCARD {
+            0..self.components.len()
+        } else {
+            // The components are sorted by ID, so those with the queried ID are adjacent.
+            let id = |c: &AppStream| c.id().to_string();
+            let start = self
+                .components
+                .partition_point(|c| id(c).as_str() < data_id.id());
+            let end = self
+                .components
+                .partition_point(|c| id(c).as_str() <= data_id.id());
+            start..end
+        };
+
+        candidates
+            .filter(|&i| self.data_ids[i].matches(data_id))
+            .map(|i| &self.components[i])
+            .collect()
+    }
+
+    /// Returns the addons which extend the component with `id`, sorted by their ID.
+    pub fn addons_of(&self, id: &Id) -> Vec<&AppStream> {
+        self.addons
+            .get(&id.to_string())
+            .map_or(&[][..], |addons| addons.as_slice())
+            .iter()
+            .map(|&i| &self.components[i])
+            .collect()
+    }
+
+    /// Returns the problems found while linking the loaded components, such as addons which
+    /// extend a component that is not in the pool.
+    pub fn issues(&self) -> &[Issue] {
+        self.issues.as_slice()
+    }
+
+    /// Returns the components which provide `value`, e.g. the media type `image/png`.
+    ///
+    /// Binaries and libraries may be given as full paths such as `/usr/bin/foo`, and a modalias
+    /// is matched against the glob patterns the components declare.
+    pub fn what_provides(&self, kind: ProvidedKind, value: &str) -> Vec<&AppStream> {
+        self.provides
+            .lookup(kind, value)
+            .into_iter()
+            .map(|i| &self.components[i])
+            .collect()
+    }
+
+    /// Searches the loaded components for all of the words in `terms`, best match first.
+    pub fn search<'a>(&'a self, terms: &str) -> Vec<SearchMatch<'a>> {
+        search::search(&self.components, terms, self.locale())
+    }
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Pool::new()
+    }
+}
+
+/// A component together with the priority, scope and path of the source it was loaded from.
+struct Entry {
+    priority: i32,
+    scope: Scope,
+    path: PathBuf,
+    component: AppStream,
+}
+
+fn load_source(
+    source: &Source,
+    options: &ParseOptions,
+    entries: &mut Vec<Entry>,
+    errors: &mut Vec<PoolError>,
+) {
+    let path = source.path.clone();
+    match source.kind {
+        SourceKind::Metainfo => {
+            let result = Metainfo::from_path(&path)
+                .map_err(|error| PoolError::Io {
+                    path: path.clone(),
+                    error,
+                })
+                .and_then(|metainfo| {
+                    metainfo
+                        .with_options(options.clone())
+                        .validate()
+                        .map_err(|error| PoolError::Metainfo {
+                            path: path.clone(),
+                            error,
+                        })
+                });
+
+            match result {
+                Ok(component) => entries.push(Entry {
+                    priority: METAINFO_PRIORITY,
+                    scope: source.scope,
+                    path,
+                    component,
+                }),
+                Err(e) => errors.push(e),
+            }
+        }
+        SourceKind::XmlCatalog => {
+            let mut stream = match Components::from_path(&path) {
+                Ok(stream) => stream.with_options(options.clone()),
+                Err(error) => return errors.push(PoolError::Io { path, error }),
+            };
+
+            let mut components = Vec::new();
+            for result in stream.by_ref() {
+                match result {
+                    Ok(component) => components.push(component),
+                    Err(error) => errors.push(PoolError::Catalog {
+                        path: path.clone(),
+                        error,
+                    }),
+                }
+            }
+
+            // The priority is known once the root element has been read.
+            let priority = stream.priority();
+            entries.extend(components.into_iter().map(|component| Entry {
+                priority,
+                scope: source.scope,
+                path: path.clone(),
+                component,
+            }));
+        }
+        SourceKind::YamlCatalog => errors.push(PoolError::Unsupported { path }),
+    }
+}
+
+/// Merges the components with the same data ID into one, and sorts the result by id and data ID.
+///
+/// Each merged component is returned with the path of the source whose data was kept.
+fn merge_entries(mut entries: Vec<Entry>) -> Vec<(DataId, PathBuf, AppStream)> {
+    // Highest priority first; the sort is stable, so ties are won by the earlier source.
+    entries.sort_by_key(|entry| Reverse(entry.priority));
+    let (merges, entries): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|entry| entry.component.merge_kind().is_some());
+
+    let mut by_id: BTreeMap<String, Vec<(DataId, PathBuf, AppStream)>> = BTreeMap::new();
+    for Entry {
+        scope,
+        path,
+        component,
+        ..
+    } in entries
+    {
+        let data_id = DataId::from_component(&component, scope.as_str());
+        by_id
+            .entry(component.id().to_string())
+            .or_default()
+            .push((data_id, path, component));
+    }
+
+    let mut merged = Vec::new();
+    for group in by_id.into_values() {
+        // Without an origin, a component cannot be told apart from the others with its id, so
+        // it only stands on its own if none of them has an origin either.
+        let mut data_ids: Vec<&DataId> = group
+            .iter()
+            .filter(|(_, _, component)| component.origin().is_some())
+            .map(|(data_id, _, _)| data_id)
+            .collect();
+        data_ids.sort();
+        data_ids.dedup();
+        if data_ids.is_empty() {
+            data_ids.push(&group[0].0);
+        }
+
+        for data_id in data_ids {
+            let mut sources = group
+                .iter()
+                .filter(|(other, _, component)| component.origin().is_none() || other == data_id);
+            let (_, path, first) = sources.next().expect("data ID without component");
+            let mut component = first.clone();
+            for (_, _, other) in sources {
+                component.fill_missing_from(other);
+            }
+            merged.push((data_id.clone(), path.clone(), component));
+        }
+    }
+
+    // Merges from higher-priority sources are applied last, so that they win.
+    for Entry { component, .. } in merges.into_iter().rev() {
+        if component.merge_kind() == Some(MergeKind::RemoveComponent) {
+            merged.retain(|(_, _, existing)| existing.id() != component.id());
+        } else {
+            for (_, _, existing) in &mut merged {
+                if existing.id() == component.id() {
+                    existing.apply_merge(&component);
+                }
+            }
+        }
+    }
+
+    merged
+}
+
+/// Links every addon to the components it extends, reporting parents which are missing.
+///
+/// `paths` holds the source of each component, to tell where a broken addon came from.
+fn link_addons(
+    components: &[AppStream],
+    paths: &[PathBuf],
+) -> (BTreeMap<String, Vec<usize>>, Vec<Issue>) {
+    let ids: BTreeSet<_> = components.iter().map(|c| c.id().to_string()).collect();
+
+    let mut addons: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    let mut issues = Vec::new();
+    for (i, component) in components.iter().enumerate() {
+        if *component.kind() != ComponentType::Addon {
+            continue;
+        }
+
+        for parent in component.extends().unwrap_or(&[]) {
+            let parent = parent.to_string();
+            if ids.contains(&parent) {
+                addons.entry(parent).or_default().push(i);
+            } else {
+                let explanation = format!(
+                    "addon `{}` from `{}` extends `{}`, which is not in the pool",
+                    component.id(),
+                    paths[i].display(),
+                    parent
+                );
+                issues.push(Issue::new(
+                    Severity::Warning,
+                    "extends-parent-missing",
+                    explanation,
+                ));
+            }
+        }
+    }
+
+    (addons, issues)
+}
+
+/// Reads the message locale from the environment, e.g. `de_DE` for `LANG=de_DE.UTF-8`.
+fn env_locale() -> Option<String> {
+    let value = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())?;
+    parse_locale(&value)
+}
+
+/// Drops the codeset from a POSIX locale but keeps its `@modifier`, so `de_DE.UTF-8@euro`
+/// becomes `de_DE@euro`. The C locale has no translations and yields `None`.
+fn parse_locale(value: &str) -> Option<String> {
+    let (base, modifier) = match value.find('@') {
+        Some(i) => value.split_at(i),
+        None => (value, ""),
+    };
+
+    match base.split('.').next().unwrap_or("") {
+        "" | "C" | "POSIX" => None,
+        language => Some(format!("{}{}", language, modifier)),
+    }
+}
+
+/// Makes an absolute directory relative, so that it can be joined onto the pool's root.
+fn strip_root(path: &Path) -> &Path {
+    path.strip_prefix("/").unwrap_or(path)
+}
+
+/// Lists the entries of `dir` in sorted order, or nothing if it does not exist.
+///
+/// A directory which cannot be read is recorded in `errors` and yields nothing.
+fn list_dir(dir: &Path, errors: &mut Vec<PoolError>) -> Vec<PathBuf> {
+    let result = fs::read_dir(dir).and_then(|entries| {
+        entries
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<Vec<_>>>()
+    });
+
+    match result {
+        Ok(mut paths) => {
+            paths.sort();
+            paths
+        }
+        // A missing directory, or a file where a directory was expected, holds no metadata.
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound || !dir.is_dir() => Vec::new(),
+        Err(error) => {
+            errors.push(PoolError::Io {
+                path: dir.to_path_buf(),
+                error,
+            });
+            Vec::new()
+        }
+    }
+}
+
+fn has_suffix(path: &Path, suffixes: &[&str]) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    suffixes.iter().any(|suffix| name.ends_with(suffix))
+}
+
+#[derive(Debug, Fail)]
+pub enum PoolError {
+    Io {
+        path: PathBuf,
+        #[cause]
+        error: io::Error,
+    },
+    Metainfo {
+        path: PathBuf,
+        #[cause]
+        error: ParseError,
+    },
+    Catalog {
+        path: PathBuf,
+        #[cause]
+        error: CatalogError,
+    },
+    Unsupported {
+        path: PathBuf,
+    },
+}
+
+impl PoolError {
+    /// Returns the file which failed to load.
+    pub fn path(&self) -> &Path {
+        match *self {
+            PoolError::Io { ref path, .. } => path,
+            PoolError::Metainfo { ref path, .. } => path,
+            PoolError::Catalog { ref path, .. } => path,
+            PoolError::Unsupported { ref path } => path,
+        }
+    }
+}
+
+impl Display for PoolError {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        let path = self.path().display();
+        match *self {
+            PoolError::Io { ref error, .. } => write!(fmt, "Failed to read `{}`: {}", path, error),
+            PoolError::Metainfo { ref error, .. } => {
+                write!(fmt, "Invalid metainfo file `{}`: {}", path, error)
+            }
+            PoolError::Catalog { ref error, .. } => {
+                write!(fmt, "Invalid catalog `{}`: {}", path, error)
+            }
+            PoolError::Unsupported { .. } => {
+                write!(
+                    fmt,
+                    "Unsupported catalog `{}`: only XML catalogs can be read",
+                    path
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+    use test_util::TempDir;
+
+    fn component(id: &str) -> String {
+        format!(
+            "<component><id>{}</id><name>App</name><summary>Does things</summary>\
+             <pkgname>app</pkgname></component>",
+            id
+        )
+    }
+
+    #[test]
+    fn locale_from_env() {
+        assert_eq!(
+            parse_locale("de_DE.UTF-8@euro"),
+            Some("de_DE@euro".to_string())
+        );
+        assert_eq!(parse_locale("en_US@euro"), Some("en_US@euro".to_string()));
+        assert_eq!(parse_locale("pt_BR.UTF-8"), Some("pt_BR".to_string()));
+        assert_eq!(parse_locale("C.UTF-8"), None);
+        assert_eq!(parse_locale("POSIX"), None);
+    }
+
+    #[test]
+    fn discover_and_load() {
+        let root = TempDir::new("pool");
+
+        let metainfo = component("org.example.Local");
+        root.write(
+            "usr/share/metainfo/org.example.Local.metainfo.xml",
+            metainfo,
+        );
+        root.write("usr/share/metainfo/README", "not metadata");
+
+        let catalog = format!(
+            "<components>{}{}</components>",
+            component("org.example.A"),
+            component("org.example.B")
+        );
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(catalog.as_bytes()).unwrap();
+        root.write(
+            "usr/share/swcatalog/xml/fedora.xml.gz",
+            gz.finish().unwrap(),
+        );
+        root.write("usr/share/swcatalog/yaml/fedora.yml", "---");
+
+        let flatpak = format!("<components>{}</components>", component("org.example.Flat"));
+        let active = "var/lib/flatpak/appstream/flathub/x86_64/active";
+        root.write(format!("{}/appstream.xml", active), flatpak);
+
+        let user = format!("<components>{}</components>", component("org.example.Mine"));
+        let active = "home/jane/.local/share/flatpak/appstream/flathub/x86_64/active";
+        root.write(format!("{}/appstream.xml", active), user);
+
+        let mut pool = Pool::new()
+            .with_root(root.path())
+            .with_home(Some("/home/jane"));
+        let (sources, errors) = pool.sources();
+        assert!(errors.is_empty());
+        let kinds: Vec<_> = sources.iter().map(|s| (s.kind(), s.scope())).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (SourceKind::Metainfo, Scope::System),
+                (SourceKind::XmlCatalog, Scope::System),
+                (SourceKind::YamlCatalog, Scope::System),
+                (SourceKind::XmlCatalog, Scope::System),
+                (SourceKind::XmlCatalog, Scope::User),
+            ]
+        );
+        assert!(sources[4].path().starts_with(root.path().join("home/jane")));
+
+        // The YAML catalog is reported rather than silently skipped.
+        let errors = pool.load();
+        assert_eq!(errors.len(), 1);
+        match errors[0] {
+            PoolError::Unsupported { ref path } => assert_eq!(path, sources[2].path()),
+            ref other => panic!("expected an unsupported source, got {:?}", other),
+        }
+
+        let ids: Vec<_> = pool
+            .components()
+            .iter()
+            .map(|c| c.id().to_string())
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                "org.example.A",
+                "org.example.B",
+                "org.example.Flat",
+                "org.example.Local",
+                "org.example.Mine"
+            ]
+        );
+
+        let pool = Pool::new().with_root(root.path()).with_home(None::<&str>);
+        assert_eq!(pool.sources().0.len(), 4);
+
+        let pool = Pool::new()
+            .with_root(root.path())
+            .with_dirs(vec![PoolDir::new("/usr/share/metainfo", DirKind::Metainfo)]);
+        assert_eq!(pool.sources().0.len(), 1);
+    }
+
+    #[test]
+    fn merge_sources() {
+        let root = TempDir::new("pool");
+
+        root.write(
+            "usr/share/metainfo/org.example.A.metainfo.xml",
+            "<component><id>org.example.A</id><name>Local A</name>\
+             <summary>Local summary</summary><license>MIT</license></component>",
+        );
+        root.write(
+            "usr/share/swcatalog/xml/high.xml",
+            format!(
+                "<components priority=\"10\">\
+                 <component><id>org.example.A</id><name>Catalog A</name>\
+                 <summary>Catalog summary</summary><pkgname>a</pkgname></component>\
+                 {}\
+                 <component merge=\"append\"><id>org.example.A</id>\
+                 <categories><category>Game</category></categories></component>\
+                 <component merge=\"remove-component\"><id>org.example.B</id></component>\
+                 </components>",
+                component("org.example.B")
+            ),
+        );
+        root.write(
+            "usr/share/swcatalog/xml/low.xml",
+            "<components>\
+             <component><id>org.example.A</id><name>Low A</name><name xml:lang=\"de\">A</name>\
+             <summary>Low summary</summary><bundle type=\"flatpak\">app/org.example.A</bundle>\
+             </component>\
+             <component merge=\"replace\"><id>org.example.A</id>\
+             <summary>Replaced summary</summary></component>\
+             </components>",
+        );
+
+        let mut pool = Pool::new().with_root(root.path());
+        assert!(pool.load().is_empty());
+
+        let components = pool.components();
+        assert_eq!(components.len(), 1);
+        let a = &components[0];
+        assert_eq!(a.name().to_string(), "Catalog A");
+        assert_eq!(a.name().get("de"), Some("A"));
+        assert_eq!(a.summary().to_string(), "Replaced summary");
+        assert_eq!(a.pkg_names().unwrap()[0].to_string(), "a");
+        assert_eq!(a.bundles().unwrap()[0].id(), "app/org.example.A");
+        assert_eq!(a.license().unwrap().to_string(), "MIT");
+        assert_eq!(a.categories().unwrap()[0].to_string(), "Game");
+        assert_eq!(a.merge_kind(), None);
+
+        let found = pool.search("catalog");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].component().id().to_string(), "org.example.A");
+        assert_eq!(pool.with_locale(Some("de")).search("a").len(), 1);
+    }
+
+    #[test]
+    fn tld_policy() {
+        let root = TempDir::new("pool-tld");
+        root.write(
+            "usr/share/metainfo/zzz.example.Local.metainfo.xml",
+            component("zzz.example.Local"),
+        );
+        let catalog = format!("<components>{}</components>", component("zzz.example.Os"));
+        root.write("usr/share/swcatalog/xml/os.xml", catalog);
+
+        let mut pool = Pool::new().with_root(root.path()).with_home(None::<&str>);
+        assert_eq!(pool.load().len(), 2);
+        assert!(pool.components().is_empty());
+
+        let mut pool = pool.with_tld_policy(TldPolicy::Permissive);
+        assert!(pool.load().is_empty());
+        assert_eq!(pool.components().len(), 2);
+    }
+
+    #[test]
+    fn what_provides() {
+        let root = TempDir::new("pool-provides");
+
+        let provider = |id: &str, provides: &str| {
+            format!(
+                "<component><id>{}</id><name>App</name><summary>Does things</summary>\
+                 <pkgname>app</pkgname><provides>{}</provides></component>",
+                id, provides
+            )
+        };
+        let catalog = format!(
+            "<components>{}{}{}</components>",
+            provider(
+                "org.example.Viewer",
+                "<mediatype>image/png</mediatype><binary>viewer</binary>"
+            ),
+            provider("org.example.Editor", "<mediatype>Image/PNG</mediatype>"),
+            provider(
+                "org.example.Firmware",
+                "<modalias>usb:v1130p0202d*</modalias><font>Example Sans</font>"
+            ),
+        );
+        root.write("usr/share/swcatalog/xml/os.xml", catalog);
+
+        let mut pool = Pool::new().with_root(root.path());
+        assert!(pool.load().is_empty());
+
+        let ids = |found: Vec<&AppStream>| -> Vec<String> {
+            found.iter().map(|c| c.id().to_string()).collect()
+        };
+        assert_eq!(
+            ids(pool.what_provides(ProvidedKind::MediaType, "image/png")),
+            vec!["org.example.Editor", "org.example.Viewer"]
+        );
+        assert_eq!(
+            ids(pool.what_provides(ProvidedKind::Binary, "/usr/bin/viewer")),
+            vec!["org.example.Viewer"]
+        );
+        assert_eq!(
+            ids(pool.what_provides(ProvidedKind::Modalias, "usb:v1130p0202d0100dc00")),
+            vec!["org.example.Firmware"]
+        );
+        assert!(pool
+            .what_provides(ProvidedKind::Modalias, "usb:v1130p0203d0100")
+            .is_empty());
+        assert_eq!(
+            ids(pool.what_provides(ProvidedKind::Font, "example sans")),
+            vec!["org.example.Firmware"]
+        );
+    }
+
+    #[test]
+    fn addons_of() {
+        let root = TempDir::new("pool-addons");
+
+        let addon = |id: &str, parent: &str| {
+            format!(
+                "<component type=\"addon\"><id>{}</id><name>Plugin</name>\
+                 <summary>Adds things</summary><pkgname>plugin</pkgname>\
+                 <extends>{}</extends></component>",
+                id, parent
+            )
+        };
+        let catalog = format!(
+            "<components>{}{}{}{}</components>",
+            component("org.example.App"),
+            addon("org.example.App.Plugin2", "org.example.App"),
+            addon("org.example.App.Plugin1", "org.example.App"),
+            addon("org.example.Orphan", "org.example.Missing"),
+        );
+        let path = root.write("usr/share/swcatalog/xml/os.xml", catalog);
+
+        let mut pool = Pool::new().with_root(root.path());
+        assert!(pool.load().is_empty());
+
+        let parent = pool.components()[0].id().clone();
+        assert_eq!(*pool.components()[0].kind(), ComponentType::Generic);
+        let ids: Vec<_> = pool
+            .addons_of(&parent)
+            .iter()
+            .map(|c| c.id().to_string())
+            .collect();
+        assert_eq!(
+            ids,
+            vec!["org.example.App.Plugin1", "org.example.App.Plugin2"]
+        );
+
+        assert_eq!(pool.issues().len(), 1);
+        assert_eq!(pool.issues()[0].tag(), "extends-parent-missing");
+        let explanation = pool.issues()[0].explanation();
+        assert!(explanation.contains(&path.display().to_string()));
+    }
+
+    #[test]
+    fn data_ids() {
+        let root = TempDir::new("pool-data-id");
+
+        let maps = |origin: &str, bundle: &str| {
+            format!(
+                "<components origin=\"{}\"><component><id>org.gnome.Maps</id>\
+                 <name>Maps</name><summary>Find places</summary>{}</component></components>",
+                origin, bundle
+            )
+        };
+        let flatpak = maps(
+            "flathub",
+            "<bundle type=\"flatpak\">app/org.gnome.Maps/x86_64/stable</bundle>",
+        );
+        root.write(
+            "var/lib/flatpak/appstream/flathub/x86_64/active/appstream.xml",
+            &flatpak,
+        );
+        root.write(
+            "home/jane/.local/share/flatpak/appstream/flathub/x86_64/active/appstream.xml",
+            &flatpak,
+        );
+        root.write(
+            "usr/share/swcatalog/xml/fedora.xml",
+            maps("fedora", "<pkgname>gnome-maps</pkgname>"),
+        );
+        let os = format!(
+            "<components origin=\"fedora\">{}</components>",
+            component("org.example.A")
+        );
+        root.write("usr/share/swcatalog/xml/os.xml", os);
+        // Without an origin, the metainfo file describes every variant of the component.
+        root.write(
+            "usr/share/metainfo/org.gnome.Maps.metainfo.xml",
+            "<component><id>org.gnome.Maps</id><name>Maps</name><summary>Find places</summary>\
+             <keywords><keyword>map</keyword></keywords></component>",
+        );
+
+        let mut pool = Pool::new()
+            .with_root(root.path())
+            .with_home(Some("/home/jane"));
+        assert!(pool.load().is_empty());
+
+        let ids: Vec<_> = pool.data_ids().iter().map(|id| id.to_string()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "system/package/fedora/org.example.A/*",
+                "system/flatpak/flathub/org.gnome.Maps/stable",
+                "system/package/fedora/org.gnome.Maps/*",
+                "user/flatpak/flathub/org.gnome.Maps/stable",
+            ]
+        );
+        assert!(pool.components()[1..]
+            .iter()
+            .all(|c| c.keywords().is_some()));
+
+        let query: DataId = "*/flatpak/*/org.gnome.Maps/*".parse().unwrap();
+        assert_eq!(pool.by_data_id(&query).len(), 2);
+        let query: DataId = "system/package/*/org.gnome.Maps/*".parse().unwrap();
+        let found = pool.by_data_id(&query);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].origin(), Some("fedora"));
+        let query: DataId = "*/*/fedora/*/*".parse().unwrap();
+        assert_eq!(pool.by_data_id(&query).len(), 2);
+        let query: DataId = "*/*/*/org.example.B/*".parse().unwrap();
+        assert!(pool.by_data_id(&query).is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+//! Full-text search over components, ranked like libappstream's search token priorities.
+
+use std::cmp::Reverse;
+
+use field::localized::Localized;
+use AppStream;
+
+/// Weights of the fields a search term can match, highest first.
+const MATCH_ID: u32 = 1 << 6;
+const MATCH_NAME: u32 = 1 << 5;
+const MATCH_KEYWORD: u32 = 1 << 4;
+const MATCH_SUMMARY: u32 = 1 << 3;
+const MATCH_DESCRIPTION: u32 = 1 << 2;
+const MATCH_PROVIDES: u32 = 1 << 1;
+const MATCH_CATEGORY: u32 = 1 << 0;
+
+/// A component which matched a search, with its relevance.
+#[derive(Clone, Copy, Debug)]
+pub struct SearchMatch<'a> {
+    component: &'a AppStream,
+    score: u32,
+}
+
+impl<'a> SearchMatch<'a> {
+    pub fn component(&self) -> &'a AppStream {
+        self.component
+    }
+
+    /// Returns the relevance of the match; higher is better.
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+}
+
+/// Splits `text` into lowercase words.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Returns the components which match every term, best match first.
+///
+/// A term matches a word of a field if the word starts with it. Each term scores the weights of
+/// all fields it matches, so `maps` ranks a component named Maps above one which mentions maps
+/// in its description. Ties keep the order of `components`.
+pub(crate) fn search<'a>(
+    components: &'a [AppStream],
+    terms: &str,
+    locale: Option<&str>,
+) -> Vec<SearchMatch<'a>> {
+    let terms = tokenize(terms);
+    if terms.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches: Vec<_> = components
+        .iter()
+        .filter_map(|component| {
+            let tokens = Tokens::new(component, locale);
+            let mut score = 0;
+            for term in &terms {
+                let matched = tokens.matches(term);
+                if matched == 0 {
+                    return None;
+                }
+                score += matched;
+            }
+
+            Some(SearchMatch { component, score })
+        })
+        .collect();
+
+    matches.sort_by_key(|m| Reverse(m.score));
+    matches
+}
+
+/// The searchable words of a component, tagged with the weight of the field they came from.
+struct Tokens(Vec<(String, u32)>);
+
+impl Tokens {
+    fn new(component: &AppStream, locale: Option<&str>) -> Self {
+        let mut tokens = Tokens(Vec::new());
+
+        tokens.add(&component.id().to_string(), MATCH_ID);
+        tokens.add_localized(component.name(), locale, MATCH_NAME);
+        tokens.add_localized(component.summary(), locale, MATCH_SUMMARY);
+        if let Some(description) = component.description() {
+            tokens.add_localized(description, locale, MATCH_DESCRIPTION);
+        }
+        if let Some(keywords) = component.keywords() {
+            let translated = locale.map_or(&[][..], |locale| keywords.get(locale));
+            for keyword in keywords.default_values().iter().chain(translated) {
+                tokens.add(keyword, MATCH_KEYWORD);
+            }
+        }
+        for provided in component.provides().unwrap_or(&[]) {
+            tokens.add(provided.value(), MATCH_PROVIDES);
+        }
+        if let Some(categories) = component.categories() {
+            for category in categories.iter() {
+                tokens.add(&category.to_string(), MATCH_CATEGORY);
+            }
+        }
+
+        tokens
+    }
+
+    fn add(&mut self, text: &str, weight: u32) {
+        let Tokens(ref mut tokens) = *self;
+        tokens.extend(tokenize(text).into_iter().map(|token| (token, weight)));
+    }
+
+    /// Adds the untranslated text and, if a locale is set, its translation, so that searching
+    /// in either language finds the component.
+    fn add_localized(&mut self, text: &Localized, locale: Option<&str>, weight: u32) {
+        let translated = locale.and_then(|locale| text.get(locale));
+        for value in text.default_value().into_iter().chain(translated) {
+            self.add(value, weight);
+        }
+    }
+
+    /// Returns the combined weight of the fields with a word starting with `term`.
+    fn matches(&self, term: &str) -> u32 {
+        let Tokens(ref tokens) = *self;
+        tokens
+            .iter()
+            .filter(|(token, _)| token.starts_with(term))
+            .fold(0, |matched, &(_, weight)| matched | weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metainfo::Metainfo;
+
+    fn component(xml: &str) -> AppStream {
+        Metainfo::from_str(format!("<component>{}</component>", xml))
+            .validate()
+            .unwrap()
+    }
+
+    #[test]
+    fn tokenize_case_folds() {
+        assert_eq!(tokenize("GNOME Maps, v2!"), vec!["gnome", "maps", "v2"]);
+        assert_eq!(tokenize("Ärger"), vec!["ärger"]);
+    }
+
+    #[test]
+    fn ranks_by_field_weight() {
+        let components = vec![
+            component(
+                "<id>org.example.Atlas</id><name>Atlas</name><summary>World atlas</summary>\
+                 <description><p>Shows maps of the world.</p></description>",
+            ),
+            component(
+                "<id>org.example.Maps</id><name>Maps</name>\
+                 <name xml:lang=\"de\">Karten</name><summary>Find places</summary>\
+                 <summary xml:lang=\"de\">Orte finden</summary>",
+            ),
+            component(
+                "<id>org.example.Editor</id><name>Editor</name><summary>Edits</summary>\
+                 <keywords><keyword>Map</keyword></keywords>\
+                 <provides><binary>editor</binary></provides>",
+            ),
+        ];
+
+        let ids = |matches: Vec<SearchMatch>| -> Vec<String> {
+            matches
+                .iter()
+                .map(|m| m.component().id().to_string())
+                .collect()
+        };
+
+        let found = search(&components, "MAP", None);
+        assert!(found[0].score() > found[1].score());
+        assert_eq!(
+            ids(found),
+            vec![
+                "org.example.Maps",
+                "org.example.Editor",
+                "org.example.Atlas"
+            ]
+        );
+
+        assert_eq!(
+            ids(search(&components, "maps world", None)),
+            vec!["org.example.Atlas"]
+        );
+        assert!(search(&components, "karten", None).is_empty());
+        assert_eq!(
+            ids(search(&components, "karten", Some("de_DE"))),
+            vec!["org.example.Maps"]
+        );
+        assert_eq!(
+            ids(search(&components, "places", Some("de_DE"))),
+            vec!["org.example.Maps"]
+        );
+    }
+}
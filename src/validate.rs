@@ -0,0 +1,423 @@
+//! Non-fatal validation which collects every issue found in a document.
+
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::path::Path;
+
+use failure::Fail;
+
+use field::id::Id;
+use field::localized::Localized;
+use location::{Location, Locator};
+use AppStream;
+
+/// How serious a validation issue is.
+///
+/// Only `Error` issues prevent a document from being accepted.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Pedantic,
+}
+
+impl Display for Severity {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        match *self {
+            Severity::Error => fmt.write_str("error"),
+            Severity::Warning => fmt.write_str("warning"),
+            Severity::Info => fmt.write_str("info"),
+            Severity::Pedantic => fmt.write_str("pedantic"),
+        }
+    }
+}
+
+/// A single problem found while validating a document.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Issue {
+    severity: Severity,
+    tag: &'static str,
+    explanation: String,
+    location: Option<Location>,
+}
+
+impl Issue {
+    pub fn new<S: Into<String>>(severity: Severity, tag: &'static str, explanation: S) -> Self {
+        Issue {
+            severity,
+            tag,
+            explanation: explanation.into(),
+            location: None,
+        }
+    }
+
+    /// Attaches the source location the issue refers to.
+    pub fn at(mut self, location: Option<Location>) -> Self {
+        self.location = location;
+        self
+    }
+
+    pub(crate) fn from_error<E: Diagnose>(error: &E) -> Self {
+        Issue::new(error.severity(), error.tag(), error.to_string())
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Returns the stable, machine-readable issue tag, e.g. `cid-has-number-prefix`.
+    pub fn tag(&self) -> &'static str {
+        self.tag
+    }
+
+    /// Returns a human readable explanation of the issue.
+    pub fn explanation(&self) -> &str {
+        &self.explanation
+    }
+
+    /// Returns the line and column the issue refers to, if known.
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, fmt: &mut Formatter) -> FmtResult {
+        if let Some(location) = self.location {
+            write!(fmt, "{}: ", location)?;
+        }
+        write!(fmt, "{}: {}: {}", self.severity, self.tag, self.explanation)
+    }
+}
+
+/// An error which can be reported as a validation issue.
+pub trait Diagnose: Fail {
+    /// Returns the stable issue tag for this error.
+    fn tag(&self) -> &'static str;
+
+    fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    /// Returns the offending value, used to pick the node to report among several matches.
+    fn subject(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The outcome of validating a document.
+#[derive(Clone, Debug)]
+pub struct Validation {
+    issues: Vec<Issue>,
+    appstream: Option<AppStream>,
+}
+
+impl Validation {
+    pub(crate) fn new(issues: Vec<Issue>, appstream: Option<AppStream>) -> Self {
+        let appstream = if issues.iter().any(|i| i.severity == Severity::Error) {
+            None
+        } else {
+            appstream
+        };
+
+        Validation { issues, appstream }
+    }
+
+    /// Returns every issue found, in the order they were detected.
+    pub fn issues(&self) -> &[Issue] {
+        self.issues.as_slice()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+
+    /// Returns the parsed document, if there were no hard errors.
+    pub fn appstream(&self) -> Option<&AppStream> {
+        self.appstream.as_ref()
+    }
+
+    pub fn into_appstream(self) -> Option<AppStream> {
+        self.appstream
+    }
+}
+
+/// Longest summary which fits into software center listings.
+const MAX_SUMMARY_LEN: usize = 35;
+
+const METAINFO_SUFFIX: &str = ".metainfo.xml";
+const LEGACY_SUFFIX: &str = ".appdata.xml";
+const METAINFO_DIR: &str = "metainfo";
+const LEGACY_DIR: &str = "appdata";
+
+/// Checks rules which only apply to components listed in a distribution catalog.
+///
+/// Catalog components must be installable, i.e. name at least one `pkgname` or `bundle`.
+pub fn catalog_issues(appstream: &AppStream) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    if appstream.pkg_names().is_none() && appstream.bundles().is_none() {
+        let explanation = format!(
+            "component `{}` in a catalog needs either a `pkgname` or a `bundle`",
+            appstream.id()
+        );
+        issues.push(Issue::new(
+            Severity::Error,
+            "catalog-component-not-installable",
+            explanation,
+        ));
+    }
+
+    issues
+}
+
+/// Checks that a metainfo file is named `<id>.metainfo.xml` and installed into
+/// `/usr/share/metainfo`.
+pub(crate) fn lint_path(path: &Path, id: Option<&Id>, issues: &mut Vec<Issue>) {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let stem = if let Some(stem) = file_name.strip_suffix(METAINFO_SUFFIX) {
+        Some(stem)
+    } else if let Some(stem) = file_name.strip_suffix(LEGACY_SUFFIX) {
+        let explanation = format!(
+            "file `{}` uses the legacy `{}` suffix instead of `{}`",
+            file_name, LEGACY_SUFFIX, METAINFO_SUFFIX
+        );
+        let issue = Issue::new(Severity::Warning, "metainfo-legacy-suffix", explanation);
+        issues.push(issue);
+        Some(stem)
+    } else {
+        let explanation = format!("file `{}` should end with `{}`", file_name, METAINFO_SUFFIX);
+        let issue = Issue::new(Severity::Warning, "metainfo-filename-invalid", explanation);
+        issues.push(issue);
+        None
+    };
+
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let in_share = |name: &str| {
+        dir.file_name() == Some(OsStr::new(name))
+            && dir.parent().and_then(|p| p.file_name()) == Some(OsStr::new("share"))
+    };
+    if in_share(LEGACY_DIR) {
+        let explanation = format!(
+            "file is installed into the legacy `/usr/share/{}` directory instead of \
+             `/usr/share/{}`",
+            LEGACY_DIR, METAINFO_DIR
+        );
+        let issue = Issue::new(Severity::Warning, "metainfo-legacy-path", explanation);
+        issues.push(issue);
+    } else if !in_share(METAINFO_DIR) {
+        let explanation = format!(
+            "file `{}` should be installed into `/usr/share/{}`",
+            path.display(),
+            METAINFO_DIR
+        );
+        let issue = Issue::new(Severity::Info, "metainfo-path-unexpected", explanation);
+        issues.push(issue);
+    }
+
+    if let (Some(stem), Some(id)) = (stem, id) {
+        let text = id.to_string();
+        if stem != text && stem != id.segments().join(".") {
+            let explanation = format!(
+                "file name `{}` does not match the component ID `{}`",
+                file_name, text
+            );
+            let issue = Issue::new(
+                Severity::Warning,
+                "metainfo-filename-cid-mismatch",
+                explanation,
+            );
+            issues.push(issue);
+        }
+    }
+}
+
+/// Runs the checks which do not prevent a field from being parsed.
+pub(crate) fn lint(appstream: &AppStream, locator: &Locator, issues: &mut Vec<Issue>) {
+    lint_id(appstream, locator, issues);
+    lint_name(appstream, locator, issues);
+    lint_summary(appstream, locator, issues);
+
+    if let Some(license) = appstream.metadata_license() {
+        if !license.is_metadata_license() {
+            let explanation = format!(
+                "metadata license `{}` is not one of the permissive licenses allowed for metadata",
+                license
+            );
+            let issue = Issue::new(Severity::Error, "metadata-license-invalid", explanation);
+            issues.push(issue.at(locator.locate("/component/metadata_license", None)));
+        }
+    }
+
+    if let Some(copyright) = appstream.copyright() {
+        for text in copyright.invalid_statements() {
+            let explanation = format!("copyright statement `{}` names no copyright holder", text);
+            let issue = Issue::new(Severity::Warning, "copyright-invalid", explanation);
+            issues.push(issue.at(locator.locate("//comment()", Some(text))));
+        }
+
+        for expr in copyright.invalid_licenses() {
+            let explanation = format!("`{}` is not a valid SPDX license expression", expr);
+            let issue = Issue::new(
+                Severity::Warning,
+                "copyright-spdx-license-invalid",
+                explanation,
+            );
+            issues.push(issue.at(locator.locate("//comment()", Some(expr))));
+        }
+    }
+
+    if let Some(categories) = appstream.categories() {
+        for cat in categories.vendor_categories() {
+            let name = cat.to_string();
+            let issue = Issue::new(
+                Severity::Warning,
+                "category-name-vendor",
+                format!("vendor category `{}` is not part of the registry", name),
+            );
+            issues.push(issue.at(locator.locate("/component/categories/category", Some(&name))));
+        }
+    }
+}
+
+fn lint_id(appstream: &AppStream, locator: &Locator, issues: &mut Vec<Issue>) {
+    let id = appstream.id();
+    let text = id.to_string();
+    let location = locator.locate("/component/id", None);
+
+    if text.chars().any(|ch| ch.is_uppercase()) {
+        let explanation = format!("component ID `{}` should only contain lowercase letters", text);
+        let issue = Issue::new(Severity::Warning, "cid-contains-uppercase-letter", explanation);
+        issues.push(issue.at(location));
+    }
+
+    if text.contains('-') {
+        let explanation = format!("component ID `{}` contains discouraged hyphens", text);
+        let issue = Issue::new(Severity::Info, "cid-contains-hyphen", explanation);
+        issues.push(issue.at(location));
+    }
+
+    if id.has_desktop_suffix() {
+        let explanation = format!("component ID `{}` has a legacy `.desktop` suffix", text);
+        let issue = Issue::new(Severity::Warning, "cid-has-desktop-suffix", explanation);
+        issues.push(issue.at(location));
+    }
+}
+
+/// Joins the lines of `text` with single spaces, as XML formatting may indent element content
+/// across several lines. Text on a single line is kept as is.
+fn collapse_whitespace(text: &str) -> String {
+    if !text.contains('\n') {
+        return text.to_string();
+    }
+
+    let lines: Vec<_> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    lines.join(" ")
+}
+
+fn lint_name(appstream: &AppStream, locator: &Locator, issues: &mut Vec<Issue>) {
+    let xpath = "/component/name";
+    let location = locator.locate(xpath, None);
+    let raw: Vec<String> = locator.reader().read("/component/name/text()").unwrap_or_default();
+
+    let trailing = |value: &String| {
+        let value = collapse_whitespace(value);
+        value.trim_end() != value
+    };
+    if raw.iter().any(trailing) {
+        let explanation = "name should not end with whitespace";
+        let issue = Issue::new(Severity::Warning, "name-has-trailing-whitespace", explanation);
+        issues.push(issue.at(location));
+    }
+
+    let name = appstream.name();
+    let values = name.default_value().into_iter().chain(name.translations().map(|(_, v)| v));
+    for value in values {
+        if value.contains("://") || value.contains("www.") {
+            let explanation = format!("name `{}` should not contain a URL", value);
+            let issue = Issue::new(Severity::Warning, "name-has-url", explanation);
+            issues.push(issue.at(locator.locate(xpath, Some(value))));
+        }
+    }
+
+    let tags = ["name-has-markup", "name-missing-untranslated"];
+    lint_localized(name, "name", tags, locator, issues);
+}
+
+fn lint_summary(appstream: &AppStream, locator: &Locator, issues: &mut Vec<Issue>) {
+    let location = locator.locate("/component/summary", None);
+    let summary = appstream.summary();
+
+    if let Some(text) = summary.default_value() {
+        if text.chars().count() > MAX_SUMMARY_LEN {
+            let explanation = format!(
+                "summary is longer than {} characters: {}",
+                MAX_SUMMARY_LEN, text
+            );
+            let issue = Issue::new(Severity::Warning, "summary-too-long", explanation);
+            issues.push(issue.at(location));
+        }
+
+        if text.ends_with('.') {
+            let explanation = format!("summary should not end with a period: {}", text);
+            let issue = Issue::new(Severity::Warning, "summary-has-dot-suffix", explanation);
+            issues.push(issue.at(location));
+        }
+
+        if let Some(name) = appstream.name().default_value() {
+            if !name.is_empty() && contains_words(text, name) {
+                let explanation = format!("summary should not repeat the name `{}`", name);
+                let issue = Issue::new(Severity::Warning, "summary-repeats-name", explanation);
+                issues.push(issue.at(location));
+            }
+        }
+    }
+
+    let tags = ["summary-has-markup", "summary-missing-untranslated"];
+    lint_localized(summary, "summary", tags, locator, issues);
+}
+
+/// Returns whether `phrase` occurs in `text` as a sequence of whole words, ignoring case.
+fn contains_words(text: &str, phrase: &str) -> bool {
+    let text = text.to_lowercase();
+    let phrase = phrase.to_lowercase();
+    let is_word_char = |ch: Option<char>| matches!(ch, Some(ch) if ch.is_alphanumeric());
+
+    text.match_indices(phrase.as_str()).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !is_word_char(before) && !is_word_char(after)
+    })
+}
+
+/// Checks a translatable element for markup and a missing untranslated value.
+///
+/// `tags` are the issue tags to use for those two problems, in that order.
+fn lint_localized(
+    value: &Localized,
+    element: &str,
+    tags: [&'static str; 2],
+    locator: &Locator,
+    issues: &mut Vec<Issue>,
+) {
+    let xpath = format!("/component/{}", element);
+    let children = format!("{}/*", xpath);
+    let markup: Vec<String> = locator.reader().read(children.as_str()).unwrap_or_default();
+
+    if !markup.is_empty() {
+        let explanation = format!("{} must be plain text without markup", element);
+        let issue = Issue::new(Severity::Warning, tags[0], explanation);
+        issues.push(issue.at(locator.locate(&children, None)));
+    }
+
+    if value.default_value().is_none() && value.translations().next().is_some() {
+        let explanation = format!("{} has translations but no untranslated value", element);
+        let issue = Issue::new(Severity::Warning, tags[1], explanation);
+        issues.push(issue.at(locator.locate(&xpath, None)));
+    }
+}